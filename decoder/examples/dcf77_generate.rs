@@ -0,0 +1,94 @@
+//! Generate a synthetic capture file of the time signal selected by the cargo
+//! features (DCF77 by default), with configurable noise, in the format recorded
+//! by `tools/dcf77_capture.py` (see `CaptureGenerator`).
+//!
+//! The frames are those of the selected time signal,
+//! with the offset of the rule of summer time of its country.
+//! The noise is made of:
+//! - jitter: highest shift, in ms, of the edges of the pulses,
+//! - dropouts: probability, in per mille, that the pulse of a second is missing,
+//! - spikes: probability, in per mille, that a polled value is flipped,
+//! - drift: error of the clock of the board, in ppm.
+//!
+//! Usage: dcf77_generate 2024-10-27T02:30+02:00 [--minutes 10] [--jitter 5]
+//! [--dropouts 5] [--spikes 2] [--drift 30] [--seed 1] > capture.txt
+use alarm_clock_decoder::{
+    dcf77::{CaptureEncoder, CaptureGenerator, Noise, SUMMER_TIME_RULE},
+    Date, Datetime, DayOfWeek, Time, UtcOffset,
+};
+use std::process::exit;
+
+/// Parse a local datetime with its offset, e.g. 2024-10-27T02:30+02:00.
+fn parse_start(start: &str) -> Option<(Datetime, UtcOffset)> {
+    let field = |range: core::ops::Range<usize>| start.get(range)?.parse::<u8>().ok();
+    let (year, offset_sign) = (start.get(0..2)?, start.get(16..17)?);
+    if year != "20" || start.len() != 22 {
+        return None;
+    }
+    let offset = field(17..19)? as i8;
+    let mut date = Date {
+        day: field(8..10)?,
+        month: field(5..7)?,
+        year: field(2..4)?,
+        weekday: DayOfWeek::Monday,
+    };
+    date.weekday = date.computed_weekday();
+    let time = Time {
+        hour: field(11..13)?,
+        minute: field(14..16)?,
+        second: Some(0),
+    };
+    let offset = match offset_sign {
+        "+" => offset,
+        "-" => -offset,
+        _ => return None,
+    };
+    Some((Datetime { date, time }, UtcOffset(offset)))
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((start, utc_offset)) = args.first().and_then(|start| parse_start(start)) else {
+        eprintln!("usage: dcf77_generate <start, e.g. 2024-10-27T02:30+02:00> [--minutes N] [--jitter MS] [--dropouts PER_MILLE] [--spikes PER_MILLE] [--drift PPM] [--seed N]");
+        exit(1);
+    };
+    let option = |name: &str, default: i64| -> i64 {
+        args.iter()
+            .position(|arg| arg == name)
+            .map(|index| {
+                args.get(index + 1)
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or_else(|| {
+                        eprintln!("bad value of {}", name);
+                        exit(1)
+                    })
+            })
+            .unwrap_or(default)
+    };
+    let minutes = option("--minutes", 5) as u16;
+    let noise = Noise {
+        jitter_ms: option("--jitter", 0) as u16,
+        dropouts: option("--dropouts", 0) as u16,
+        spikes: option("--spikes", 0) as u16,
+        drift_ppm: option("--drift", 0) as i16,
+    };
+    let seed = option("--seed", 0) as u32;
+
+    println!(
+        "# dcf77 synthetic capture {} minutes={} jitter={} dropouts={} spikes={} drift={} seed={}",
+        args[0], minutes, noise.jitter_ms, noise.dropouts, noise.spikes, noise.drift_ppm, seed
+    );
+    let mut encoder = CaptureEncoder::default();
+    let generator =
+        CaptureGenerator::new(&start, utc_offset, SUMMER_TIME_RULE, minutes, noise, seed);
+    let mut lines: Vec<_> = generator
+        .filter_map(|(timer, value)| encoder.push(timer, value))
+        .collect();
+    lines.extend(encoder.finish());
+    for line in lines {
+        let mut text = String::new();
+        ufmt::uwrite!(&mut text, "{}", line).ok();
+        // Capture files hold the lines streamed by the board, without the leading `$`.
+        println!("{}", text.trim_start_matches('$'));
+    }
+}
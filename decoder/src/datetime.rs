@@ -0,0 +1,456 @@
+//! Datetime structure and methods
+pub use summer_time::{
+    SummerTimeRule, TransitionRule, WeekOfMonth, EU_SUMMER_TIME_RULE, JAPAN_TIME_RULE,
+    UK_SUMMER_TIME_RULE, US_EASTERN_SUMMER_TIME_RULE,
+};
+
+mod summer_time;
+
+/// Datetime structure
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Datetime {
+    pub date: Date,
+    pub time: Time,
+}
+
+/// Dev-friendly representation of the day of the week
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DayOfWeek {
+    Monday = 1,
+    Tuesday = 2,
+    Wednesday = 3,
+    Thursday = 4,
+    Friday = 5,
+    Saturday = 6,
+    Sunday = 7,
+}
+
+impl TryFrom<u8> for DayOfWeek {
+    type Error = ();
+
+    /// Convert an u8 to a DayOfWeek
+    fn try_from(weekday: u8) -> Result<Self, Self::Error> {
+        match weekday {
+            1 => Ok(DayOfWeek::Monday),
+            2 => Ok(DayOfWeek::Tuesday),
+            3 => Ok(DayOfWeek::Wednesday),
+            4 => Ok(DayOfWeek::Thursday),
+            5 => Ok(DayOfWeek::Friday),
+            6 => Ok(DayOfWeek::Saturday),
+            7 => Ok(DayOfWeek::Sunday),
+            _ => Err(()),
+        }
+    }
+}
+
+impl DayOfWeek {
+    /// True for week-end, false otherwise.
+    pub fn is_week_end(&self) -> bool {
+        matches!(self, DayOfWeek::Saturday | DayOfWeek::Sunday)
+    }
+
+    /// Day of the week following this one.
+    pub fn next(&self) -> Self {
+        match self {
+            DayOfWeek::Monday => DayOfWeek::Tuesday,
+            DayOfWeek::Tuesday => DayOfWeek::Wednesday,
+            DayOfWeek::Wednesday => DayOfWeek::Thursday,
+            DayOfWeek::Thursday => DayOfWeek::Friday,
+            DayOfWeek::Friday => DayOfWeek::Saturday,
+            DayOfWeek::Saturday => DayOfWeek::Sunday,
+            DayOfWeek::Sunday => DayOfWeek::Monday,
+        }
+    }
+
+    /// Day of the week preceding this one.
+    pub fn previous(&self) -> Self {
+        match self {
+            DayOfWeek::Monday => DayOfWeek::Sunday,
+            DayOfWeek::Tuesday => DayOfWeek::Monday,
+            DayOfWeek::Wednesday => DayOfWeek::Tuesday,
+            DayOfWeek::Thursday => DayOfWeek::Wednesday,
+            DayOfWeek::Friday => DayOfWeek::Thursday,
+            DayOfWeek::Saturday => DayOfWeek::Friday,
+            DayOfWeek::Sunday => DayOfWeek::Saturday,
+        }
+    }
+}
+
+/// Offset of the local time with respect to UTC, in hours
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct UtcOffset(pub i8);
+
+impl UtcOffset {
+    /// Central European Time, UTC+1
+    pub const CET: UtcOffset = UtcOffset(1);
+    /// Central European Summer Time, UTC+2
+    pub const CEST: UtcOffset = UtcOffset(2);
+    /// Greenwich Mean Time, UTC+0
+    pub const GMT: UtcOffset = UtcOffset(0);
+    /// British Summer Time, UTC+1
+    pub const BST: UtcOffset = UtcOffset(1);
+    /// Japan Standard Time, UTC+9
+    pub const JST: UtcOffset = UtcOffset(9);
+
+    /// Offset expressed in minutes.
+    pub fn minutes(&self) -> i16 {
+        60 * self.0 as i16
+    }
+}
+
+/// Date structure
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Date {
+    pub day: u8,
+    pub month: u8,
+    pub year: u8,
+    pub weekday: DayOfWeek,
+}
+
+impl core::ops::Sub for Date {
+    type Output = i16;
+
+    /// Number of days between two dates.
+    fn sub(self, rhs: Self) -> Self::Output {
+        // The fixed days exceed i16 at the end of the century
+        self.fixed_from_gregorian()
+            .wrapping_sub(rhs.fixed_from_gregorian()) as i16
+    }
+}
+
+impl Date {
+    /// Number of days in the month of the date.
+    /// Years are within 2000-2099, so every fourth year is a leap year.
+    pub fn days_in_month(&self) -> u8 {
+        match self.month {
+            2 if self.year % 4 == 0 => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+
+    /// Day of the week, computed from the date (rather than read from the field).
+    pub fn computed_weekday(&self) -> DayOfWeek {
+        // Cumulative number of days at the beginning of each month, non-leap year
+        const DAYS_BEFORE_MONTH: [u16; 12] =
+            [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+        let year = self.year as u16;
+        let month_index = (self.month.clamp(1, 12) - 1) as usize;
+        let mut days_since_2000 =
+            365 * year + (year + 3) / 4 + DAYS_BEFORE_MONTH[month_index] + self.day as u16;
+        if self.month > 2 && self.year % 4 == 0 {
+            days_since_2000 += 1;
+        }
+        // 2000-01-01 (day 1) was a Saturday
+        match (days_since_2000 + 4) % 7 {
+            0 => DayOfWeek::Monday,
+            1 => DayOfWeek::Tuesday,
+            2 => DayOfWeek::Wednesday,
+            3 => DayOfWeek::Thursday,
+            4 => DayOfWeek::Friday,
+            5 => DayOfWeek::Saturday,
+            _ => DayOfWeek::Sunday,
+        }
+    }
+
+    /// Date of the following day.
+    pub fn next_day(&self) -> Self {
+        let mut out = *self;
+        out.weekday = self.weekday.next();
+        if self.day < self.days_in_month() {
+            out.day += 1;
+        } else if self.month < 12 {
+            out.day = 1;
+            out.month += 1;
+        } else {
+            out.day = 1;
+            out.month = 1;
+            out.year = (self.year + 1) % 100;
+        }
+        out
+    }
+
+    /// Date of the preceding day.
+    pub fn previous_day(&self) -> Self {
+        let mut out = *self;
+        out.weekday = self.weekday.previous();
+        if self.day > 1 {
+            out.day -= 1;
+        } else if self.month > 1 {
+            out.month -= 1;
+            out.day = out.days_in_month();
+        } else {
+            out.month = 12;
+            out.day = 31;
+            out.year = (self.year + 99) % 100;
+        }
+        out
+    }
+
+    /// Number of days from an arbitrary, fixed day,
+    /// suitable to computations of differences.
+    fn fixed_from_gregorian(&self) -> u16 {
+        // Leap years before this one, the year 0 (2000) being leap
+        let leap_years = (self.year as u16 + 3) / 4;
+        let mut out = 365 * self.year as u16
+            + leap_years
+            + (367 * self.month as u16 - 362) / 12
+            + self.day as u16;
+
+        if self.month > 2 {
+            if self.year % 4 == 0 {
+                out -= 1
+            } else {
+                out -= 2
+            }
+        }
+        out
+    }
+}
+
+/// Time structure, optional second.
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub struct Time {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: Option<u8>,
+}
+
+impl core::ops::Sub for Time {
+    type Output = i16;
+
+    /// Number of minutes between two times,
+    fn sub(self, rhs: Self) -> Self::Output {
+        let lhs = 60 * self.hour as i16 + self.minute as i16;
+        let rhs = 60 * rhs.hour as i16 + rhs.minute as i16;
+        lhs - rhs
+    }
+}
+
+impl Datetime {
+    /// Datetime at the beginning of a minute, from the year 2000, with its weekday,
+    /// if the fields are within their ranges (the day within its month).
+    pub fn at_minute(year: u8, month: u8, day: u8, hour: u8, minute: u8) -> Option<Self> {
+        let mut date = Date {
+            day,
+            month,
+            year,
+            weekday: DayOfWeek::Monday,
+        };
+        if year > 99
+            || !(1..=12).contains(&month)
+            || !(1..=date.days_in_month()).contains(&day)
+            || hour > 23
+            || minute > 59
+        {
+            return None;
+        }
+        date.weekday = date.computed_weekday();
+        Some(Datetime {
+            date,
+            time: Time {
+                hour,
+                minute,
+                second: Some(0),
+            },
+        })
+    }
+
+    /// Shift the datetime by a (signed) number of minutes,
+    /// the seconds being left untouched.
+    pub fn add_minutes(&self, minutes: i16) -> Self {
+        let mut date = self.date;
+        let mut minutes_of_day = 60 * self.time.hour as i16 + self.time.minute as i16 + minutes;
+        while minutes_of_day < 0 {
+            minutes_of_day += 24 * 60;
+            date = date.previous_day();
+        }
+        while minutes_of_day >= 24 * 60 {
+            minutes_of_day -= 24 * 60;
+            date = date.next_day();
+        }
+        Datetime {
+            date,
+            time: Time {
+                hour: (minutes_of_day / 60) as u8,
+                minute: (minutes_of_day % 60) as u8,
+                second: self.time.second,
+            },
+        }
+    }
+
+    /// Convert a local datetime to UTC, given its offset.
+    pub fn to_utc(&self, offset: UtcOffset) -> Self {
+        self.add_minutes(-offset.minutes())
+    }
+
+    /// Convert a datetime in UTC to local time, given the offset.
+    pub fn to_local(&self, offset: UtcOffset) -> Self {
+        self.add_minutes(offset.minutes())
+    }
+}
+
+impl core::ops::Sub for Datetime {
+    type Output = Option<i16>;
+
+    /// Number of minutes between two datetimes.
+    fn sub(self, rhs: Self) -> Self::Output {
+        let days_diff_in_minutes = (self.date - rhs.date).checked_mul(24 * 60)?;
+        let time_diff = self.time - rhs.time;
+        Some(days_diff_in_minutes + time_diff)
+    }
+}
+
+/// Phase of day, refining the notion of an alarm
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PhaseOfDay {
+    /// Default phase
+    Default {
+        /// Day of month when the last transition occured,
+        /// used to avoid triggering multiples changes of phase
+        /// on the same day.
+        day_last_set: Option<u8>,
+    },
+    /// Dawn: the LED strip is to simulate the raising light
+    Dawn {
+        /// Number of minutes since the beginning of the dawn.
+        elapsed_since_dawn: u8,
+    },
+    /// Sunrise: the alarm is to be triggered during this phase,
+    /// until it is acked and comes back to default.
+    SunRise {
+        /// Number of minutes since sunrise.
+        elapsed_since_sunrise: u8,
+    },
+}
+
+/// Datetime of the clock, along with its trustworthiness
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ClockDatetime {
+    /// The RTC could not be read
+    Unavailable,
+    /// The oscillator of the RTC stopped since it was last set
+    /// (for instance, its battery failed): the datetime is wrong
+    Untrusted(Datetime),
+    /// The RTC kept running since it was last set
+    Trusted(Datetime),
+}
+
+impl ClockDatetime {
+    /// Datetime, only if trustworthy.
+    pub fn trusted(&self) -> Option<Datetime> {
+        match self {
+            ClockDatetime::Trusted(datetime) => Some(*datetime),
+            _ => None,
+        }
+    }
+
+    /// Datetime read from the RTC, even if not trustworthy.
+    pub fn unchecked(&self) -> Option<Datetime> {
+        match self {
+            ClockDatetime::Trusted(datetime) | ClockDatetime::Untrusted(datetime) => {
+                Some(*datetime)
+            }
+            ClockDatetime::Unavailable => None,
+        }
+    }
+}
+
+impl ufmt::uDisplay for Date {
+    /// Format a date to display on the serial port,
+    /// for instance 2023-12-07
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        if self.year < 10 {
+            f.write_str("200")?;
+        } else {
+            f.write_str("20")?;
+        }
+        self.year.fmt(f)?;
+
+        if self.month < 10 {
+            f.write_str("-0")?;
+        } else {
+            f.write_str("-")?;
+        }
+        self.month.fmt(f)?;
+
+        if self.day < 10 {
+            f.write_str("-0")?;
+        } else {
+            f.write_str("-")?;
+        }
+        self.day.fmt(f)?;
+
+        Ok(())
+    }
+}
+
+impl ufmt::uDisplay for Time {
+    /// Format a time to display on the serial port.
+    /// for instance 21:34
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        if self.hour < 10 {
+            f.write_str("0")?;
+        }
+        self.hour.fmt(f)?;
+
+        if self.minute < 10 {
+            f.write_str(":0")?;
+        } else {
+            f.write_str(":")?;
+        }
+        self.minute.fmt(f)?;
+
+        if let Some(second) = self.second {
+            if second < 10 {
+                f.write_str(":0")?;
+            } else {
+                f.write_str(":")?;
+            }
+            second.fmt(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ufmt::uDisplay for UtcOffset {
+    /// Format an offset to display on the serial port.
+    /// for instance +02:00
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        let hours = self.0.unsigned_abs();
+        match (self.0 < 0, hours < 10) {
+            (true, true) => f.write_str("-0")?,
+            (true, false) => f.write_str("-")?,
+            (false, true) => f.write_str("+0")?,
+            (false, false) => f.write_str("+")?,
+        }
+        hours.fmt(f)?;
+        f.write_str(":00")
+    }
+}
+
+impl ufmt::uDisplay for Datetime {
+    /// Format a datetime to display on the serial port.
+    /// for instance 2023-12-07T21:34
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        self.date.fmt(f)?;
+        f.write_str("T")?;
+        self.time.fmt(f)?;
+
+        Ok(())
+    }
+}
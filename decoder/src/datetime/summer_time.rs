@@ -0,0 +1,143 @@
+//! Rules of summer time, to derive the offset of the local time
+//! from UTC when no radio signal gives it.
+use super::{Date, Datetime, DayOfWeek, UtcOffset};
+
+/// Week of the month when a transition occurs
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum WeekOfMonth {
+    First,
+    Second,
+    Third,
+    Fourth,
+    Last,
+}
+
+/// Rule of a transition: a given day of the week (for instance the last Sunday)
+/// of a month, at a given hour in UTC.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TransitionRule {
+    pub month: u8,
+    pub week: WeekOfMonth,
+    pub weekday: DayOfWeek,
+    pub hour_utc: u8,
+}
+
+/// Rule of summer time: offsets of standard and summer time,
+/// and the transitions between both.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SummerTimeRule {
+    /// Offset during the standard (winter) time
+    pub standard: UtcOffset,
+    /// Offset during the summer time
+    pub summer: UtcOffset,
+    /// Transition from standard to summer time
+    pub start: TransitionRule,
+    /// Transition from summer to standard time
+    pub end: TransitionRule,
+}
+
+/// Rule in force in the European Union: from the last Sunday of March
+/// to the last Sunday of October, at 01:00 UTC.
+pub const EU_SUMMER_TIME_RULE: SummerTimeRule = SummerTimeRule {
+    standard: UtcOffset::CET,
+    summer: UtcOffset::CEST,
+    start: TransitionRule {
+        month: 3,
+        week: WeekOfMonth::Last,
+        weekday: DayOfWeek::Sunday,
+        hour_utc: 1,
+    },
+    end: TransitionRule {
+        month: 10,
+        week: WeekOfMonth::Last,
+        weekday: DayOfWeek::Sunday,
+        hour_utc: 1,
+    },
+};
+
+/// Rule in force in the United Kingdom (MSF): from the last Sunday of March
+/// to the last Sunday of October, at 01:00 UTC.
+pub const UK_SUMMER_TIME_RULE: SummerTimeRule = SummerTimeRule {
+    standard: UtcOffset::GMT,
+    summer: UtcOffset::BST,
+    ..EU_SUMMER_TIME_RULE
+};
+
+/// Rule in force in the Eastern time zone of the United States (WWVB):
+/// from the second Sunday of March to the first Sunday of November,
+/// at 02:00 local time.
+pub const US_EASTERN_SUMMER_TIME_RULE: SummerTimeRule = SummerTimeRule {
+    standard: UtcOffset(-5),
+    summer: UtcOffset(-4),
+    start: TransitionRule {
+        month: 3,
+        week: WeekOfMonth::Second,
+        weekday: DayOfWeek::Sunday,
+        hour_utc: 7,
+    },
+    end: TransitionRule {
+        month: 11,
+        week: WeekOfMonth::First,
+        weekday: DayOfWeek::Sunday,
+        hour_utc: 6,
+    },
+};
+
+/// Japan (JJY) has no summer time: both offsets are the Japan Standard Time.
+pub const JAPAN_TIME_RULE: SummerTimeRule = SummerTimeRule {
+    standard: UtcOffset::JST,
+    summer: UtcOffset::JST,
+    ..EU_SUMMER_TIME_RULE
+};
+
+impl TransitionRule {
+    /// Day of the month of the transition, for a given year.
+    fn day_of_month(&self, year: u8) -> u8 {
+        let first_of_month = Date {
+            year,
+            month: self.month,
+            day: 1,
+            weekday: DayOfWeek::Monday,
+        };
+        // Number of days from the first of the month to the first expected weekday
+        let shift = (7 + self.weekday as u8 - first_of_month.computed_weekday() as u8) % 7;
+        let first_occurrence = 1 + shift;
+
+        match self.week {
+            WeekOfMonth::First => first_occurrence,
+            WeekOfMonth::Second => first_occurrence + 7,
+            WeekOfMonth::Third => first_occurrence + 14,
+            WeekOfMonth::Fourth => first_occurrence + 21,
+            WeekOfMonth::Last => {
+                let days_in_month = first_of_month.days_in_month();
+                first_occurrence + 7 * ((days_in_month - first_occurrence) / 7)
+            }
+        }
+    }
+
+    /// True if the transition has occurred in the year of the given datetime (in UTC).
+    fn has_occurred(&self, utc: &Datetime) -> bool {
+        let day = self.day_of_month(utc.date.year);
+        (utc.date.month, utc.date.day, utc.time.hour) >= (self.month, day, self.hour_utc)
+    }
+}
+
+impl SummerTimeRule {
+    /// Offset of the local time for a given datetime in UTC.
+    pub fn utc_offset(&self, utc: &Datetime) -> UtcOffset {
+        let started = self.start.has_occurred(utc);
+        let ended = self.end.has_occurred(utc);
+        // In the southern hemisphere, summer time spans over the new year
+        let is_summer_time = if self.start.month <= self.end.month {
+            started && !ended
+        } else {
+            started || !ended
+        };
+
+        if is_summer_time {
+            self.summer
+        } else {
+            self.standard
+        }
+    }
+}
@@ -0,0 +1,438 @@
+//! DCF77 decoder: take as input the bits polled from the receiver,
+//! and return a complete datetime.
+//! Other time signals (MSF, WWVB, JJY) are decoded the same way,
+//! when selected by a cargo feature (see `time_signal`).
+pub use self::capture::{CaptureEncoder, CaptureSamples};
+#[cfg(feature = "dcf77-tx")]
+pub use self::encoder::Dcf77Encoder;
+#[cfg(feature = "generator")]
+pub use self::generator::{CaptureGenerator, Noise};
+pub use self::metrics::Dcf77Metrics;
+#[cfg(not(feature = "dcf77-int0"))]
+use self::phase_detector::PhaseDetector;
+use self::plausibility::Plausibility;
+use self::pll::Pll;
+#[cfg(not(feature = "dcf77-int0"))]
+use self::polled_values::PolledValues;
+pub use self::protocol::{Dcf77Flags, Dcf77Frame, Dcf77SignalVariant};
+use self::protocol::{ProtocolError, Signals};
+#[cfg(feature = "dcf77-int0")]
+use self::pulses::PulseDecoder;
+use self::time_signal::{SelectedSignal, TimeSignal};
+use super::{
+    datetime,
+    timer::{Timer, POLLED_SAMPLES_PERIOD_MS},
+    Datetime, SummerTimeRule,
+};
+use ringbuffer::RingBuffer;
+
+pub const POLLED_SAMPLES_FREQUENCY: usize = 1000 / POLLED_SAMPLES_PERIOD_MS as usize;
+/// Rule of summer time, applied when DCF77 does not provide the offset to UTC
+#[cfg(not(any(feature = "msf", feature = "wwvb", feature = "jjy")))]
+pub const SUMMER_TIME_RULE: SummerTimeRule = datetime::EU_SUMMER_TIME_RULE;
+/// Rule of summer time, applied when MSF does not provide the offset to UTC
+#[cfg(feature = "msf")]
+pub const SUMMER_TIME_RULE: SummerTimeRule = datetime::UK_SUMMER_TIME_RULE;
+/// Rule of summer time, giving the offsets of the local time to the UTC of WWVB
+#[cfg(feature = "wwvb")]
+pub const SUMMER_TIME_RULE: SummerTimeRule = datetime::US_EASTERN_SUMMER_TIME_RULE;
+/// Rule of summer time (none in Japan), applied when JJY is not received
+#[cfg(feature = "jjy")]
+pub const SUMMER_TIME_RULE: SummerTimeRule = datetime::JAPAN_TIME_RULE;
+/// Acceptance window of the spacing between peaks, in ms,
+/// accounting for the inaccuracy of the internal clock
+#[cfg(not(feature = "dcf77-int0"))]
+const PEAK_SPACING_MS: (u16, u16) = (900, 1100);
+/// Highest number of seconds without an accepted peak
+/// for the locked tolerance to apply
+#[cfg(not(feature = "dcf77-int0"))]
+const LOCKED_MAX_MISSED_SECONDS: u16 = 10;
+
+mod capture;
+#[cfg(feature = "dcf77-tx")]
+mod encoder;
+#[cfg(feature = "generator")]
+mod generator;
+mod metrics;
+#[cfg(not(feature = "dcf77-int0"))]
+mod phase_detector;
+mod plausibility;
+mod pll;
+#[cfg(not(feature = "dcf77-int0"))]
+mod polled_values;
+mod protocol;
+#[cfg(feature = "dcf77-int0")]
+mod pulses;
+mod time_signal;
+
+/// Errors that may arise during the decoding process.
+/// None of them resets the decoder: the affected bits are marked as unknown.
+#[derive(Clone, Copy)]
+pub enum WorkflowError {
+    MissedPolledValue(Timer),
+    LastPeakTooClose(Timer),
+    LastPeakTooFar(Timer),
+    BadBit(u8),
+    BadPulseWidth(Timer),
+    Protocol(ProtocolError),
+}
+
+impl WorkflowError {
+    /// Number of variants, protocol errors excluded
+    pub const COUNT: usize = 5;
+
+    /// Index of the variant, none for protocol errors
+    /// (counted by `ProtocolError::index`)
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            WorkflowError::MissedPolledValue(_) => Some(0),
+            WorkflowError::LastPeakTooClose(_) => Some(1),
+            WorkflowError::LastPeakTooFar(_) => Some(2),
+            WorkflowError::BadBit(_) => Some(3),
+            WorkflowError::BadPulseWidth(_) => Some(4),
+            WorkflowError::Protocol(_) => None,
+        }
+    }
+}
+
+impl From<ProtocolError> for WorkflowError {
+    fn from(e: ProtocolError) -> Self {
+        Self::Protocol(e)
+    }
+}
+
+impl ufmt::uDisplay for WorkflowError {
+    /// Format the error to display on the serial.
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        match self {
+            WorkflowError::MissedPolledValue(ms) => {
+                f.write_str("missed polled value ").and_then(|_| ms.fmt(f))
+            }
+            WorkflowError::LastPeakTooClose(ms) => {
+                f.write_str("last peak too close ").and_then(|_| ms.fmt(f))
+            }
+            WorkflowError::LastPeakTooFar(ms) => {
+                f.write_str("last peak too far ").and_then(|_| ms.fmt(f))
+            }
+            WorkflowError::BadBit(count) => f.write_str("bad bit ").and_then(|_| count.fmt(f)),
+            WorkflowError::BadPulseWidth(ms) => {
+                f.write_str("bad pulse width ").and_then(|_| ms.fmt(f))
+            }
+            WorkflowError::Protocol(e) => e.fmt(f),
+        }
+    }
+}
+
+/// Dcf77 decoder main structure
+#[derive(Default)]
+pub struct Dcf77 {
+    /// Time of the last polled value
+    #[cfg(not(feature = "dcf77-int0"))]
+    last_update_timer: Option<Timer>,
+    /// Array holding the past boolean polled values of the DFC77
+    /// pin input at a "high" rate
+    #[cfg(not(feature = "dcf77-int0"))]
+    polled_values: PolledValues,
+    /// Detector of the phase of the signal, that is,
+    /// detect the beginning of UTC seconds among the polled values
+    #[cfg(not(feature = "dcf77-int0"))]
+    phase_detector: PhaseDetector,
+    /// Time of the last detected peak of the correlation
+    /// between the samples and the pattern, signaling a new bit
+    #[cfg(not(feature = "dcf77-int0"))]
+    last_peak_update: Option<Timer>,
+    /// Array holding the decoded, 1Hz-bits of the DCF77 signal
+    signals: Signals,
+    /// Last bit pushed in the array of signals
+    previous_bit: Option<Dcf77SignalVariant>,
+    /// Check of the consistency between successive frames
+    plausibility: Plausibility,
+    /// Phase-locked loop disciplining the internal timer
+    pll: Pll,
+    /// Decoder of the pulses, when the edges of the input are timestamped
+    #[cfg(feature = "dcf77-int0")]
+    pulses: PulseDecoder,
+    /// New correction of the rate of the timer, not yet applied
+    pending_timer_correction: Option<i16>,
+    /// Metrics of the quality of the reception
+    metrics: Dcf77Metrics,
+    /// New polled value processed during the last run, if any
+    #[cfg(not(feature = "dcf77-int0"))]
+    last_sample: Option<(Timer, bool)>,
+}
+
+impl Dcf77 {
+    /// Main call, with the latest polled value and its timer, if any:
+    /// on the target, as published by the timer interrupt, on a host,
+    /// from a capture (see `CaptureSamples`).
+    /// The current datetime of the RTC, if any, is used
+    /// to check the plausibility of a decoded frame.
+    #[cfg(not(feature = "dcf77-int0"))]
+    pub fn run(
+        &mut self,
+        sample: Option<(Timer, bool)>,
+        rtc: Option<Datetime>,
+    ) -> Result<(Option<Dcf77SignalVariant>, Option<Dcf77Frame>), WorkflowError> {
+        let result = self.decode(sample, rtc);
+        self.record_result(&result);
+        self.metrics.record_phase(
+            self.phase_detector.threshold_margin(),
+            self.phase_detector.lock_confidence(),
+            self.phase_detector.is_locked(),
+        );
+        self.metrics
+            .record_pll(self.pll.correction(), self.pll.last_error());
+        result
+    }
+
+    /// Main call when the edges of the input are timestamped by the external
+    /// interrupt (feature `dcf77-int0`), with the latest edge, if any:
+    /// its timer and the logical level after it.
+    /// The current datetime of the RTC, if any, is used
+    /// to check the plausibility of a decoded frame.
+    #[cfg(feature = "dcf77-int0")]
+    pub fn run_edge(
+        &mut self,
+        edge: Option<(Timer, bool)>,
+        rtc: Option<Datetime>,
+    ) -> Result<(Option<Dcf77SignalVariant>, Option<Dcf77Frame>), WorkflowError> {
+        let result = match self.pulses.process_edge(edge) {
+            Ok(Some(bit)) => self.decode_bit(bit, rtc),
+            Ok(None) => Ok((None, None)),
+            Err(e) => {
+                match e {
+                    WorkflowError::LastPeakTooFar(diff) => {
+                        // Some pulses were missed: the bits of the missed seconds,
+                        // and of the previous pulse, are unknown.
+                        let seconds = (diff.0 + 500) / 1000;
+                        for _ in 0..core::cmp::min(seconds as usize, Signals::CAPACITY) {
+                            self.push_signal(Dcf77SignalVariant::Unknown);
+                        }
+                    }
+                    WorkflowError::BadPulseWidth(_) => {
+                        self.push_signal(Dcf77SignalVariant::Unknown);
+                    }
+                    _ => (),
+                }
+                Err(e)
+            }
+        };
+        // The rising edges of the pulses, timestamped by the interrupt,
+        // mark the seconds more accurately than the peaks of the polled values.
+        if let Some(second_mark) = self.pulses.take_second_mark() {
+            if let Some(correction) = self.pll.record_peak(second_mark) {
+                self.pending_timer_correction = Some(correction);
+            }
+        }
+        self.record_result(&result);
+        self.metrics
+            .record_pll(self.pll.correction(), self.pll.last_error());
+        result
+    }
+
+    /// Record the result of a run in the metrics.
+    fn record_result(
+        &mut self,
+        result: &Result<(Option<Dcf77SignalVariant>, Option<Dcf77Frame>), WorkflowError>,
+    ) {
+        match result {
+            Ok((Some(bit), _)) => self.metrics.record_bit(*bit),
+            Ok((None, _)) => (),
+            Err(e) => self.metrics.record_error(e),
+        }
+    }
+
+    /// Metrics of the quality of the reception.
+    pub fn metrics(&self) -> &Dcf77Metrics {
+        &self.metrics
+    }
+
+    /// New correction of the rate of the internal timer, in 1/65536 ms per ms,
+    /// to apply (see `timer::set_correction`), if any since the last call.
+    pub fn take_timer_correction(&mut self) -> Option<i16> {
+        self.pending_timer_correction.take()
+    }
+
+    /// New polled value processed during the last run, with its timer, if any.
+    #[cfg(not(feature = "dcf77-int0"))]
+    pub fn last_sample(&self) -> Option<(Timer, bool)> {
+        self.last_sample
+    }
+
+    /// Process a new polled value, if any, and decode the signal.
+    #[cfg(not(feature = "dcf77-int0"))]
+    fn decode(
+        &mut self,
+        sample: Option<(Timer, bool)>,
+        rtc: Option<Datetime>,
+    ) -> Result<(Option<Dcf77SignalVariant>, Option<Dcf77Frame>), WorkflowError> {
+        // sample becomes Some almost immediately after the boot
+        self.last_sample = None;
+        if let Some((timer, polled_value)) = sample {
+            if self.last_update_timer != Some(timer) {
+                self.last_sample = Some((timer, polled_value));
+            }
+            if let Some(bit) = self.process_new_polled_values(timer, polled_value)? {
+                // A new bit has been detected.
+                return self.decode_bit(bit, rtc);
+            }
+        }
+
+        Ok((None, None))
+    }
+
+    /// Push a new bit in the array of signals, and decode it.
+    fn decode_bit(
+        &mut self,
+        bit: Dcf77SignalVariant,
+        rtc: Option<Datetime>,
+    ) -> Result<(Option<Dcf77SignalVariant>, Option<Dcf77Frame>), WorkflowError> {
+        let bit = SelectedSignal::follow(self.previous_bit, bit);
+        self.push_signal(bit);
+
+        // Decode the array of bits as a frame (if possible), the few unknown bits
+        // being taken from the frame predicted from the previous minute.
+        // Publish it only if it is consistent with the previous one,
+        // fully received.
+        let prediction = self
+            .plausibility
+            .prediction()
+            .and_then(|p| SelectedSignal::encode(&p));
+        let minute = self
+            .signals
+            .get_minute::<SelectedSignal>(prediction.as_ref());
+        let frame = minute
+            .as_ref()
+            .map(|(minute, _)| SelectedSignal::decode(minute))
+            .transpose()?;
+        if frame.is_some() {
+            self.metrics.record_decoded_frame();
+        }
+        let reconstructed = matches!(minute, Some((_, true)));
+        let frame = frame.and_then(|frame| self.plausibility.check(frame, reconstructed, rtc));
+        Ok((Some(bit), frame))
+    }
+
+    /// Push a new bit in the array of signals.
+    fn push_signal(&mut self, bit: Dcf77SignalVariant) {
+        self.signals.push(bit);
+        self.previous_bit = Some(bit);
+        // All the bits recorded before the beginning of a minute are useless.
+        self.signals.clear_up_to_first_minute_end();
+    }
+
+    /// Number of decoded frames rejected by the plausibility check.
+    pub fn rejected_frames(&self) -> u16 {
+        self.plausibility.rejected_frames()
+    }
+
+    /// Detect if the container of the polled value holds a new one;
+    /// if so, process it, and if a new bit of the signal, return it.
+    #[cfg(not(feature = "dcf77-int0"))]
+    pub fn process_new_polled_values(
+        &mut self,
+        current_timer: Timer,
+        polled_value: bool,
+    ) -> Result<Option<Dcf77SignalVariant>, WorkflowError> {
+        // A new polled value is expected at exactly
+        // a difference of POLLED_SAMPLES_PERIOD_MS.
+        if let Some(last_update_timer) = self.last_update_timer {
+            let diff = current_timer - last_update_timer;
+            if diff < Timer(POLLED_SAMPLES_PERIOD_MS) {
+                return Ok(None);
+            }
+            if diff > Timer(POLLED_SAMPLES_PERIOD_MS) {
+                // Keep the samples aligned in time, by repeating
+                // the current value for the missed ones.
+                let missed = (diff.0 / POLLED_SAMPLES_PERIOD_MS - 1) as usize;
+                for _ in 0..core::cmp::min(missed, POLLED_SAMPLES_FREQUENCY) {
+                    self.polled_values.update(polled_value);
+                }
+                self.polled_values.update(polled_value);
+                self.last_update_timer = Some(current_timer);
+                return Err(WorkflowError::MissedPolledValue(diff));
+            }
+        }
+        self.last_update_timer = Some(current_timer);
+        self.polled_values.update(polled_value);
+
+        // To find the phase, the scalar product of the current bits
+        // (of the polled values) with as predefined pattern is computed,
+        // for instance:
+        // ...**......*........*......**... samples
+        // **......**......**......**...... pattern
+        // This correlation is at a maximum (a peak) when the pattern
+        // and the samples are timely aligned.
+        match self
+            .polled_values
+            .scalar_product(&SelectedSignal::PHASE_PATTERN)
+        {
+            None => Ok(None),
+            Some(sp) => {
+                if !self.phase_detector.detect_peak(sp) {
+                    // Wait for a subsequent polled value to detect a peak.
+                    return Ok(None);
+                }
+                self.metrics
+                    .record_peak_height(self.phase_detector.last_peak_height());
+
+                // Try to avoid false-positives, we expect 1s between peaks,
+                // plus a margin to account for the inaccuracy of the internal
+                // clock, or a whole number of seconds once it is disciplined.
+                if let Some(last_peak_update_timer) = self.last_peak_update {
+                    let diff = current_timer - last_peak_update_timer;
+                    let seconds = (diff.0 + 500) / 1000;
+                    let tolerance = self.pll.peak_tolerance_ms(seconds).filter(|_| {
+                        self.phase_detector.is_locked() && seconds <= LOCKED_MAX_MISSED_SECONDS
+                    });
+                    if let Some(tolerance) = tolerance {
+                        let offset = diff.0 as i32 - seconds as i32 * 1000;
+                        if seconds == 0 || offset < -tolerance {
+                            // Spurious peak: ignore it, keeping the previous one as reference.
+                            return Err(WorkflowError::LastPeakTooClose(diff));
+                        }
+                        if offset > tolerance {
+                            // Spurious peak as well, between two seconds.
+                            return Err(WorkflowError::LastPeakTooFar(diff));
+                        }
+                        // The bits of the seconds whose peak was missed are unknown,
+                        // the current one is still in phase.
+                        for _ in 1..seconds {
+                            self.push_signal(Dcf77SignalVariant::Unknown);
+                        }
+                    } else if diff < Timer(PEAK_SPACING_MS.0) {
+                        // Spurious peak: ignore it, keeping the previous one as reference.
+                        return Err(WorkflowError::LastPeakTooClose(diff));
+                    } else if diff > Timer(PEAK_SPACING_MS.1) {
+                        // Some peaks were missed: the bits of the missed seconds,
+                        // and of the current one, are unknown.
+                        for _ in 0..core::cmp::min(seconds as usize, Signals::CAPACITY) {
+                            self.push_signal(Dcf77SignalVariant::Unknown);
+                        }
+                        self.last_peak_update = self.last_update_timer;
+                        return Err(WorkflowError::LastPeakTooFar(diff));
+                    }
+                }
+
+                self.last_peak_update = self.last_update_timer;
+                if self.phase_detector.is_locked() {
+                    if let Some(correction) = self.pll.record_peak(current_timer) {
+                        self.pending_timer_correction = Some(correction);
+                    }
+                }
+                // Return the last (most recent) identified bit of the signal,
+                // or mark it as unknown if it cannot be identified.
+                match SelectedSignal::classify(&self.polled_values) {
+                    Ok(bit) => Ok(Some(bit)),
+                    Err(e) => {
+                        self.push_signal(Dcf77SignalVariant::Unknown);
+                        Err(e)
+                    }
+                }
+            }
+        }
+    }
+}
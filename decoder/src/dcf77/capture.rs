@@ -0,0 +1,192 @@
+//! Capture of the raw polled values of DCF77, streamed over the serial port
+//! in a compact text format, one line per run of consecutive samples:
+//!
+//! `$<timer of the first sample> <number of samples> <samples in hex>`
+//!
+//! Samples are packed by four per hexadecimal digit, the oldest one in the
+//! most significant bit, the last digit being padded with zeros.
+//! A line holds at most one second of samples; a new line begins when
+//! a sample was missed. For instance: `$1025 40 f00000f000`
+//!
+//! Recorded on a host (see `tools/dcf77_capture.py`), the same lines without
+//! the leading `$` make a capture file, which can be replayed through the
+//! decoder with `CaptureSamples`. Lines beginning with `#` are comments.
+use super::POLLED_SAMPLES_FREQUENCY;
+use crate::timer::{Timer, POLLED_SAMPLES_PERIOD_MS};
+
+/// Number of bytes holding the samples of a line
+const CAPTURE_LINE_BYTES: usize = POLLED_SAMPLES_FREQUENCY / 8;
+
+/// Run of consecutive samples
+#[derive(Clone, Copy)]
+pub struct CaptureLine {
+    /// Timer of the first sample
+    first_timer: Timer,
+    /// Number of samples of the line
+    count: u8,
+    /// Bit-array of the samples, the oldest one in the most significant bit
+    samples: [u8; CAPTURE_LINE_BYTES],
+}
+
+impl CaptureLine {
+    /// Create a line holding a single sample.
+    fn new(timer: Timer, value: bool) -> Self {
+        let mut out = Self {
+            first_timer: timer,
+            count: 0,
+            samples: [0; CAPTURE_LINE_BYTES],
+        };
+        out.push(value);
+        out
+    }
+
+    /// Append a sample to the line.
+    fn push(&mut self, value: bool) {
+        if value {
+            let index = self.count as usize;
+            self.samples[index / 8] |= 0x80 >> (index % 8);
+        }
+        self.count += 1;
+    }
+
+    /// True if no more sample can be appended.
+    fn is_full(&self) -> bool {
+        self.count as usize == POLLED_SAMPLES_FREQUENCY
+    }
+}
+
+impl ufmt::uDisplay for CaptureLine {
+    /// Format the line to send it on the serial port.
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+        f.write_char('$')?;
+        self.first_timer.fmt(f)?;
+        f.write_char(' ')?;
+        self.count.fmt(f)?;
+        f.write_char(' ')?;
+        for nibble_index in 0..(self.count as usize + 3) / 4 {
+            let byte = self.samples[nibble_index / 2];
+            let nibble = if nibble_index % 2 == 0 {
+                byte >> 4
+            } else {
+                byte & 0x0f
+            };
+            f.write_char(HEX_DIGITS[nibble as usize] as char)?;
+        }
+        Ok(())
+    }
+}
+
+/// Encoder of the polled values into capture lines
+#[derive(Default)]
+pub struct CaptureEncoder {
+    /// Line being filled
+    line: Option<CaptureLine>,
+    /// Timer of the last sample pushed
+    last_timer: Option<Timer>,
+}
+
+impl CaptureEncoder {
+    /// Push a new sample, and return a line when it is complete.
+    pub fn push(&mut self, timer: Timer, value: bool) -> Option<CaptureLine> {
+        let consecutive = self
+            .last_timer
+            .map(|last_timer| timer - last_timer == Timer(POLLED_SAMPLES_PERIOD_MS))
+            .unwrap_or(false);
+        self.last_timer = Some(timer);
+
+        match self.line.as_mut() {
+            Some(line) if consecutive => {
+                line.push(value);
+                if line.is_full() {
+                    self.line.take()
+                } else {
+                    None
+                }
+            }
+            _ => self.line.replace(CaptureLine::new(timer, value)),
+        }
+    }
+
+    /// Return the line being filled, if any, to end the capture.
+    pub fn finish(&mut self) -> Option<CaptureLine> {
+        self.last_timer = None;
+        self.line.take()
+    }
+}
+
+/// Iterator over the samples of a capture file,
+/// malformed lines being skipped.
+pub struct CaptureSamples<'a> {
+    /// Remaining lines of the capture
+    lines: core::str::Lines<'a>,
+    /// Timer of the next sample of the current line
+    timer: Timer,
+    /// Index of the next sample of the current line
+    index: u8,
+    /// Number of samples of the current line
+    count: u8,
+    /// Hexadecimal digits of the current line
+    digits: &'a [u8],
+}
+
+impl<'a> CaptureSamples<'a> {
+    /// Iterate over the samples of the content of a capture file.
+    pub fn new(capture: &'a str) -> Self {
+        Self {
+            lines: capture.lines(),
+            timer: Timer::default(),
+            index: 0,
+            count: 0,
+            digits: &[],
+        }
+    }
+
+    /// Parse a line of the capture: timer, number of samples and hexadecimal digits.
+    fn parse_line(line: &'a str) -> Option<(Timer, u8, &'a [u8])> {
+        let mut fields = line.trim().trim_start_matches('$').split(' ');
+        let timer = fields.next()?.parse().ok()?;
+        let count: u8 = fields.next()?.parse().ok()?;
+        let digits = fields.next()?.as_bytes();
+        if digits.len() != (count as usize + 3) / 4
+            || !digits.iter().all(u8::is_ascii_hexdigit)
+            || fields.next().is_some()
+        {
+            return None;
+        }
+        Some((Timer(timer), count, digits))
+    }
+}
+
+impl<'a> Iterator for CaptureSamples<'a> {
+    type Item = (Timer, bool);
+
+    /// Next sample of the capture, with its timer.
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index >= self.count {
+            let line = self.lines.next()?;
+            if line.starts_with('#') {
+                continue;
+            }
+            if let Some((timer, count, digits)) = Self::parse_line(line) {
+                self.timer = timer;
+                self.index = 0;
+                self.count = count;
+                self.digits = digits;
+            }
+        }
+
+        // The digits were checked by `parse_line`.
+        let nibble = (self.digits[self.index as usize / 4] as char)
+            .to_digit(16)
+            .unwrap_or(0);
+        let value = nibble & (0x8 >> (self.index % 4)) != 0;
+        let timer = self.timer;
+        self.index += 1;
+        self.timer = self.timer + Timer(POLLED_SAMPLES_PERIOD_MS);
+        Some((timer, value))
+    }
+}
@@ -0,0 +1,97 @@
+//! DCF77 encoder: turn a local datetime into the pulses of the DCF77 signal,
+//! to re-broadcast the time, for instance to radio clocks out of reach
+//! of the emitter. The encoding is the inverse of `Datetime::try_from(Protocol)`.
+use super::{protocol::Protocol, Dcf77Frame};
+use crate::{Datetime, SummerTimeRule, Time, UtcOffset};
+
+/// Width, in ms, of the pulse (reduced carrier) of a low bit
+const LOW_BIT_MS: u16 = 100;
+/// Width, in ms, of the pulse (reduced carrier) of a high bit
+const HIGH_BIT_MS: u16 = 200;
+
+/// Encoder of the seconds of the signal
+pub struct Dcf77Encoder {
+    /// Minute being transmitted (local time), if any
+    minute: Option<Datetime>,
+    /// Bits transmitted during this minute
+    bits: [bool; 59],
+}
+
+impl Default for Dcf77Encoder {
+    fn default() -> Self {
+        Self {
+            minute: None,
+            bits: [false; 59],
+        }
+    }
+}
+
+impl Dcf77Encoder {
+    /// Encode a frame into the 59 bits of the protocol,
+    /// the bits of the civil warnings being left to zero.
+    pub fn encode(frame: &Dcf77Frame) -> [bool; 59] {
+        Protocol::from(frame).bits
+    }
+
+    /// Width, in ms, of the pulse at the beginning of the second of a local datetime,
+    /// if any: there is no pulse during the last second of the minute.
+    pub fn pulse_ms(
+        &mut self,
+        datetime: &Datetime,
+        utc_offset: UtcOffset,
+        rule: &SummerTimeRule,
+    ) -> Option<u16> {
+        let minute = Datetime {
+            time: Time {
+                second: None,
+                ..datetime.time
+            },
+            ..*datetime
+        };
+        if self.minute != Some(minute) {
+            self.minute = Some(minute);
+            self.bits = Self::encode(&Dcf77Frame::transmitted_during(&minute, utc_offset, rule));
+        }
+        let second = datetime.time.second.unwrap_or_default();
+        self.bits
+            .get(second as usize)
+            .map(|&bit| if bit { HIGH_BIT_MS } else { LOW_BIT_MS })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datetime::EU_SUMMER_TIME_RULE;
+
+    /// Local datetime, from the year 2000, at the beginning of a minute.
+    fn local(year: u8, month: u8, day: u8, hour: u8, minute: u8) -> Datetime {
+        Datetime::at_minute(year, month, day, hour, minute).unwrap()
+    }
+
+    #[test]
+    fn encoded_frames_are_decoded_back() {
+        for (datetime, utc_offset) in [
+            (local(0, 1, 1, 0, 0), UtcOffset::CET),
+            (local(24, 2, 29, 23, 59), UtcOffset::CET),
+            (local(24, 3, 31, 1, 59), UtcOffset::CET),
+            (local(24, 10, 27, 2, 59), UtcOffset::CEST),
+            (local(99, 12, 31, 23, 58), UtcOffset::CET),
+        ] {
+            let frame = Dcf77Frame::transmitted_during(&datetime, utc_offset, &EU_SUMMER_TIME_RULE);
+            let bits = Dcf77Encoder::encode(&frame);
+            let Ok(decoded) = Dcf77Frame::try_from(Protocol { bits }) else {
+                panic!("encoded frame not decoded");
+            };
+            // The seconds are not transmitted.
+            assert!(decoded.datetime.date == frame.datetime.date);
+            assert_eq!(decoded.datetime.time.hour, frame.datetime.time.hour);
+            assert_eq!(decoded.datetime.time.minute, frame.datetime.time.minute);
+            assert_eq!(decoded.utc_offset.0, frame.utc_offset.0);
+            assert_eq!(
+                decoded.flags.summer_time_announcement,
+                frame.flags.summer_time_announcement
+            );
+        }
+    }
+}
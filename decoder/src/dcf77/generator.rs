@@ -0,0 +1,169 @@
+//! Generator of synthetic captures of the time signal selected by the cargo
+//! features: the polled values of a board receiving the signal of a range
+//! of minutes, with some noise, to test the decoder on a host (see
+//! `examples/dcf77_generate.rs`). Each minute is encoded with the inverse
+//! of the decoding (see `TimeSignal::transmitted_during`).
+use super::{
+    time_signal::{SelectedSignal, TimeSignal},
+    Dcf77SignalVariant,
+};
+use crate::{
+    timer::{Timer, POLLED_SAMPLES_PERIOD_MS},
+    Datetime, SummerTimeRule, UtcOffset,
+};
+
+/// Streams of random numbers, one per kind of noise,
+/// and for the jitter, one per edge of the pulses of a second
+const JITTER: [u32; 4] = [1, 2, 5, 6];
+const DROPOUT: u32 = 3;
+const SPIKE: u32 = 4;
+
+/// Noise added to the signal
+#[derive(Default, Clone, Copy)]
+pub struct Noise {
+    /// Highest shift, in ms, of the edges of the pulses
+    pub jitter_ms: u16,
+    /// Probability, in per mille, that the pulse of a second is missing
+    pub dropouts: u16,
+    /// Probability, in per mille, that a polled value is flipped
+    pub spikes: u16,
+    /// Error of the internal clock of the board, in ppm
+    pub drift_ppm: i16,
+}
+
+/// Iterator over the polled values of a synthetic capture, with their timer,
+/// from one second before the first minute to the end of the last one.
+pub struct CaptureGenerator {
+    /// Beginning of the first minute, in UTC
+    start: Datetime,
+    /// Number of minutes transmitted
+    minutes: u16,
+    /// Rule of summer time of the local time transmitted
+    rule: SummerTimeRule,
+    /// Noise added to the signal
+    noise: Noise,
+    /// Seed of the noise
+    seed: u32,
+    /// Index of the next polled value
+    index: u32,
+    /// Timer of the next polled value
+    timer: Timer,
+    /// Index of the last minute encoded, with its symbols
+    symbols: Option<(i64, [Dcf77SignalVariant; 60])>,
+}
+
+impl CaptureGenerator {
+    /// Generate the signal of a number of minutes, from the beginning
+    /// of the minute of a local datetime, with its offset to UTC.
+    pub fn new(
+        start: &Datetime,
+        utc_offset: UtcOffset,
+        rule: SummerTimeRule,
+        minutes: u16,
+        noise: Noise,
+        seed: u32,
+    ) -> Self {
+        Self {
+            start: start.to_utc(utc_offset),
+            minutes,
+            rule,
+            noise,
+            seed,
+            index: 0,
+            timer: Timer(POLLED_SAMPLES_PERIOD_MS),
+            symbols: None,
+        }
+    }
+
+    /// Random number, reproducible from the seed, the stream and the index
+    /// (hash "lowbias32" of the three of them).
+    fn random(&self, stream: u32, index: i64) -> u32 {
+        let hash = |mut x: u32| {
+            x ^= x >> 16;
+            x = x.wrapping_mul(0x7feb_352d);
+            x ^= x >> 15;
+            x = x.wrapping_mul(0x846c_a68b);
+            x ^ (x >> 16)
+        };
+        hash(hash(self.seed ^ hash(stream)) ^ index as u32)
+    }
+
+    /// Shift, in ms, of an edge of the pulses of a second.
+    fn jitter(&self, stream: u32, second: i64) -> i64 {
+        let jitter_ms = self.noise.jitter_ms as i64;
+        (self.random(stream, second) % (2 * jitter_ms as u32 + 1)) as i64 - jitter_ms
+    }
+
+    /// Symbol transmitted during a second, if its pulses are not missing.
+    fn symbol(&mut self, second: i64) -> Option<Dcf77SignalVariant> {
+        let (minute, second_of_minute) = (second / 60, (second % 60) as usize);
+        if minute >= self.minutes as i64
+            || self.random(DROPOUT, second) % 1000 < self.noise.dropouts as u32
+        {
+            return None;
+        }
+        let symbols = match self.symbols {
+            Some((index, symbols)) if index == minute => symbols,
+            _ => {
+                let utc = self.start.add_minutes(minute as i16);
+                let utc_offset = self.rule.utc_offset(&utc);
+                let symbols = SelectedSignal::transmitted_during(
+                    &utc.to_local(utc_offset),
+                    utc_offset,
+                    &self.rule,
+                );
+                self.symbols = Some((minute, symbols));
+                symbols
+            }
+        };
+        Some(symbols[second_of_minute])
+    }
+
+    /// Logical level of the signal at an instant, in ms
+    /// from the beginning of the first minute.
+    fn level(&mut self, instant: i64) -> bool {
+        // The pulse of the next second may begin early.
+        let second = instant.div_euclid(1000);
+        [second, second + 1]
+            .into_iter()
+            .filter(|&second| second >= 0)
+            .any(|second| {
+                let Some(symbol) = self.symbol(second) else {
+                    return false;
+                };
+                SelectedSignal::high_levels(symbol)
+                    .iter()
+                    .zip(JITTER.chunks(2))
+                    .any(|(&(begin, end), streams)| {
+                        let begin = 1000 * second + begin as i64 + self.jitter(streams[0], second);
+                        let end = 1000 * second + end as i64 + self.jitter(streams[1], second);
+                        (begin..end).contains(&instant)
+                    })
+            })
+    }
+}
+
+impl Iterator for CaptureGenerator {
+    type Item = (Timer, bool);
+
+    /// Next polled value, the majority of the levels of the signal
+    /// sampled every ms of the board since the previous one.
+    fn next(&mut self) -> Option<Self::Item> {
+        let period = POLLED_SAMPLES_PERIOD_MS as i64;
+        let board_ms = period * self.index as i64 - 1000;
+        let true_ms = board_ms + board_ms * self.noise.drift_ppm as i64 / 1_000_000;
+        if true_ms >= 60_000 * self.minutes as i64 {
+            return None;
+        }
+        let high = (0..period).filter(|ms| self.level(true_ms - ms)).count() as i64;
+        let mut value = 2 * high > period;
+        if self.random(SPIKE, self.index as i64) % 1000 < self.noise.spikes as u32 {
+            value = !value;
+        }
+
+        let timer = self.timer;
+        self.index += 1;
+        self.timer = self.timer + Timer(POLLED_SAMPLES_PERIOD_MS);
+        Some((timer, value))
+    }
+}
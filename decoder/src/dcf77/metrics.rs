@@ -0,0 +1,100 @@
+//! Metrics of the quality of the reception of DCF77.
+use super::{Dcf77SignalVariant, ProtocolError, WorkflowError};
+
+/// Weight of the past in the rolling quality score, as a power of two
+const QUALITY_HISTORY_SHIFT: u8 = 4;
+
+/// Counters and indicators of the reception quality
+#[derive(Default, Clone, Copy)]
+pub struct Dcf77Metrics {
+    /// Number of workflow errors, by variant (see `WorkflowError::index`)
+    pub workflow_errors: [u16; WorkflowError::COUNT],
+    /// Number of protocol errors, by variant (see `ProtocolError::index`)
+    pub protocol_errors: [u16; ProtocolError::COUNT],
+    /// Height of the last peak detected by the phase detector
+    pub last_peak_height: u8,
+    /// Highest peak detected by the phase detector since the boot
+    pub max_peak_height: u8,
+    /// Margin of the threshold of the phase detector above the moving average
+    pub threshold_margin: u8,
+    /// Confidence of the phase detector in the current phase, from 0 to 100
+    pub lock_confidence: u8,
+    /// Whether the phase detector is locked on the signal
+    pub locked: bool,
+    /// Correction of the rate of the internal timer, in 1/65536 ms per ms
+    pub timer_correction: i16,
+    /// Error of the internal timer measured over the last window
+    /// of the phase-locked loop, in ms per 64 s, if any
+    pub pll_error: Option<i16>,
+    /// Number of minutes ends received, each one closing a candidate frame
+    pub frames_attempted: u16,
+    /// Number of frames decoded without protocol error
+    pub frames_decoded: u16,
+    /// Rolling average of the quality of the bits, scaled by the history weight
+    quality_accumulator: u16,
+}
+
+impl Dcf77Metrics {
+    /// Record a bit of the signal.
+    pub fn record_bit(&mut self, bit: Dcf77SignalVariant) {
+        if bit == Dcf77SignalVariant::MinuteEnd {
+            self.frames_attempted = self.frames_attempted.saturating_add(1);
+        }
+        self.update_quality(bit != Dcf77SignalVariant::Unknown);
+    }
+
+    /// Record an error of the decoder.
+    pub fn record_error(&mut self, error: &WorkflowError) {
+        if let WorkflowError::Protocol(e) = error {
+            let count = &mut self.protocol_errors[e.index()];
+            *count = count.saturating_add(1);
+        } else if let Some(index) = error.index() {
+            let count = &mut self.workflow_errors[index];
+            *count = count.saturating_add(1);
+            self.update_quality(false);
+        }
+    }
+
+    /// Record a frame decoded without error.
+    pub fn record_decoded_frame(&mut self) {
+        self.frames_decoded = self.frames_decoded.saturating_add(1);
+    }
+
+    /// Record the height of a peak of the phase detector.
+    pub fn record_peak_height(&mut self, height: u8) {
+        self.last_peak_height = height;
+        self.max_peak_height = core::cmp::max(self.max_peak_height, height);
+    }
+
+    /// Record the state of the phase detector.
+    pub fn record_phase(&mut self, threshold_margin: u8, lock_confidence: u8, locked: bool) {
+        self.threshold_margin = threshold_margin;
+        self.lock_confidence = lock_confidence;
+        self.locked = locked;
+    }
+
+    /// Record the state of the phase-locked loop.
+    pub fn record_pll(&mut self, timer_correction: i16, pll_error: Option<i16>) {
+        self.timer_correction = timer_correction;
+        self.pll_error = pll_error;
+    }
+
+    /// Correction of the rate of the internal timer, in ppm.
+    pub fn timer_correction_ppm(&self) -> i32 {
+        // 1_000_000 / 65536 = 15625 / 1024
+        (self.timer_correction as i32 * 15625) >> 10
+    }
+
+    /// Rolling quality score, from 0 (no valid bit) to 100 (only valid bits).
+    pub fn quality(&self) -> u8 {
+        (self.quality_accumulator >> QUALITY_HISTORY_SHIFT) as u8
+    }
+
+    /// Update the rolling average with a good or a bad event.
+    fn update_quality(&mut self, good: bool) {
+        self.quality_accumulator -= self.quality_accumulator >> QUALITY_HISTORY_SHIFT;
+        if good {
+            self.quality_accumulator += 100;
+        }
+    }
+}
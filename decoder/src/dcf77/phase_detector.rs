@@ -0,0 +1,198 @@
+//! Phase detector, to detect the beginning of a second
+//! in the polled bits.
+use super::POLLED_SAMPLES_FREQUENCY;
+use ringbuffer::{ConstGenericRingBuffer, RingBuffer};
+
+/// Lowest margin of the threshold above the moving average,
+/// used when the scalar products are not noisy
+const MIN_THRESHOLD_MARGIN: u8 = 2;
+/// Highest margin of the threshold above the moving average:
+/// the peaks of a decent signal are at least 16 above the moving average
+const MAX_THRESHOLD_MARGIN: u8 = 12;
+/// Weight of the past in the rolling spread of the ripples, as a power of two
+const RIPPLE_HISTORY_SHIFT: u8 = 4;
+/// Tolerance, in number of samples, on the spacing of consecutive peaks
+/// (a multiple of one second) to consider them consistent
+const PEAK_SPACING_TOLERANCE: u16 = 2;
+/// Highest lock confidence
+const MAX_LOCK_CONFIDENCE: u8 = 8;
+/// Lock confidence from which the detector is considered locked on the signal
+const LOCKED_CONFIDENCE: u8 = 4;
+
+/// Structure for the phase detector
+pub(super) struct PhaseDetector {
+    /// Buffer of the previous scalar products
+    sp_buffer: ConstGenericRingBuffer<u8, POLLED_SAMPLES_FREQUENCY>,
+    /// Previous height above moving average, used to detect peaks
+    prev_height_above_ma: u8,
+    /// Don't detect more than one peak during one second
+    peak_detected_for_current_second: bool,
+    /// Height above the moving average of the last detected peak
+    last_peak_height: u8,
+    /// Previous scalar product, used to detect the ripples
+    prev_scalar_product: u8,
+    /// Whether the scalar products were rising before the previous one
+    rising: bool,
+    /// Rolling mean of the square of the height of the ripples
+    /// above the moving average, scaled by the history weight
+    ripple_square_accumulator: u16,
+    /// Current margin of the threshold above the moving average
+    threshold_margin: u8,
+    /// Number of samples since the last peak consistent with the previous ones
+    samples_since_reference_peak: u16,
+    /// Confidence in the current phase, from 0 to MAX_LOCK_CONFIDENCE
+    lock_confidence: u8,
+}
+
+impl Default for PhaseDetector {
+    /// Initialize a default phase detector
+    fn default() -> Self {
+        Self {
+            sp_buffer: ConstGenericRingBuffer::new(),
+            prev_height_above_ma: 0,
+            peak_detected_for_current_second: false,
+            last_peak_height: 0,
+            prev_scalar_product: 0,
+            rising: false,
+            ripple_square_accumulator: 0,
+            threshold_margin: MIN_THRESHOLD_MARGIN,
+            samples_since_reference_peak: 0,
+            lock_confidence: 0,
+        }
+    }
+}
+
+impl PhaseDetector {
+    /// Return true if a peak is detected (and, when locked, consistent with the phase).
+    /// In our caracterisation, a peak is found when the scalar product given as argument
+    /// is, for the first time, lower than the previous one.
+    /// To eliminate spurious detections from ripples, we want the scalar product to be "much" larger
+    /// than a typical threshold value, a shifted moving average.
+    pub fn detect_peak(&mut self, scalar_product: u8) -> bool {
+        self.sp_buffer.push(scalar_product);
+        if !self.sp_buffer.is_full() {
+            self.prev_scalar_product = scalar_product;
+            return false;
+        }
+        self.samples_since_reference_peak = self.samples_since_reference_peak.saturating_add(1);
+
+        // Compute the typical value of the scalar product
+        let moving_average =
+            self.sp_buffer.iter().map(|v| *v as u16).sum::<u16>() / POLLED_SAMPLES_FREQUENCY as u16;
+        let moving_average = moving_average as u8;
+
+        // Compute the height of the scalar product above a threshold.
+        // The shift above the moving average follows the noise of the scalar products,
+        // so that the ripples are eliminated, even with a noisy signal.
+        let height_above_ma =
+            scalar_product.saturating_sub(moving_average.saturating_add(self.threshold_margin));
+
+        // Core of the detection
+        let candidate = !self.peak_detected_for_current_second
+            && self.prev_height_above_ma > 0
+            && scalar_product < self.prev_scalar_product;
+        let mut peak_found = false;
+        if candidate {
+            self.last_peak_height = self.prev_height_above_ma;
+            if self.update_lock() {
+                self.peak_detected_for_current_second = true;
+                peak_found = true;
+            } else {
+                // A peak inconsistent with the locked phase: a ripple above the threshold,
+                // which must not hide the right peak of the current second
+                self.update_threshold(self.prev_scalar_product.saturating_sub(moving_average));
+            }
+        } else if self.rising
+            && scalar_product < self.prev_scalar_product
+            && self.prev_height_above_ma == 0
+        {
+            // A local maximum below the threshold: a ripple
+            self.update_threshold(self.prev_scalar_product.saturating_sub(moving_average));
+        }
+        if scalar_product <= moving_average {
+            // When the scalar product returns below the moving average
+            // (with an hysteresis, to ignore the dips of a noisy peak),
+            // we consider that a new second ahs begun
+            self.peak_detected_for_current_second = false;
+        }
+        self.check_missed_peak();
+
+        self.prev_height_above_ma = height_above_ma;
+        if scalar_product != self.prev_scalar_product {
+            self.rising = scalar_product > self.prev_scalar_product;
+        }
+        self.prev_scalar_product = scalar_product;
+
+        peak_found
+    }
+
+    /// Height above the moving average of the last detected peak.
+    pub fn last_peak_height(&self) -> u8 {
+        self.last_peak_height
+    }
+
+    /// Current margin of the threshold above the moving average.
+    pub fn threshold_margin(&self) -> u8 {
+        self.threshold_margin
+    }
+
+    /// Confidence in the current phase, from 0 (searching) to 100.
+    pub fn lock_confidence(&self) -> u8 {
+        (self.lock_confidence as u16 * 100 / MAX_LOCK_CONFIDENCE as u16) as u8
+    }
+
+    /// Return true if the detector is locked on the signal,
+    /// false if it is still searching for the phase.
+    pub fn is_locked(&self) -> bool {
+        self.lock_confidence >= LOCKED_CONFIDENCE
+    }
+
+    /// Update the threshold with the height of a ripple above the moving average:
+    /// the margin is set to three times the spread (root mean square) of the ripples, plus one.
+    fn update_threshold(&mut self, ripple_height: u8) {
+        let ripple_height = core::cmp::min(ripple_height, MAX_THRESHOLD_MARGIN) as u16;
+        self.ripple_square_accumulator -= self.ripple_square_accumulator >> RIPPLE_HISTORY_SHIFT;
+        self.ripple_square_accumulator += ripple_height * ripple_height;
+        let mean_square = self.ripple_square_accumulator >> RIPPLE_HISTORY_SHIFT;
+
+        // Integer square root, the mean square being small
+        let mut spread = 0;
+        while (spread + 1) * (spread + 1) <= mean_square {
+            spread += 1;
+        }
+        self.threshold_margin =
+            (3 * spread as u8 + 1).clamp(MIN_THRESHOLD_MARGIN, MAX_THRESHOLD_MARGIN);
+    }
+
+    /// Update the lock confidence with a new peak: it is consistent
+    /// with the reference one if they are spaced by a multiple of one second.
+    /// Return false if the detector is locked and the peak is not consistent.
+    fn update_lock(&mut self) -> bool {
+        let offset = self.samples_since_reference_peak % POLLED_SAMPLES_FREQUENCY as u16;
+        let deviation = core::cmp::min(offset, POLLED_SAMPLES_FREQUENCY as u16 - offset);
+        if deviation <= PEAK_SPACING_TOLERANCE {
+            self.lock_confidence = core::cmp::min(self.lock_confidence + 1, MAX_LOCK_CONFIDENCE);
+            self.samples_since_reference_peak = 0;
+            true
+        } else if self.is_locked() {
+            self.lock_confidence -= 1;
+            false
+        } else {
+            // Still searching: the new peak may be the right one
+            self.lock_confidence = self.lock_confidence.saturating_sub(1);
+            self.samples_since_reference_peak = 0;
+            true
+        }
+    }
+
+    /// Decrease the lock confidence when no peak came one second
+    /// after the reference one.
+    fn check_missed_peak(&mut self) {
+        if self.samples_since_reference_peak % POLLED_SAMPLES_FREQUENCY as u16
+            == PEAK_SPACING_TOLERANCE + 1
+            && self.samples_since_reference_peak > POLLED_SAMPLES_FREQUENCY as u16
+        {
+            self.lock_confidence = self.lock_confidence.saturating_sub(1);
+        }
+    }
+}
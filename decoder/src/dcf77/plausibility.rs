@@ -0,0 +1,94 @@
+//! Plausibility check of the decoded datetimes: a single frame
+//! is not trusted, it has to be consistent with a previous one
+//! (or with the current time of the RTC) to be published.
+use super::Dcf77Frame;
+use crate::Datetime;
+
+/// Maximal difference, in minutes, between a decoded frame
+/// and the current time of the RTC to consider them consistent.
+const RTC_TOLERANCE_MINUTES: i16 = 1;
+
+/// Last decoded frame, with the way it was received
+#[derive(Clone, Copy)]
+struct LastFrame {
+    frame: Dcf77Frame,
+    /// Some of its bits were taken from the prediction
+    reconstructed: bool,
+    /// It was consistent, and published
+    published: bool,
+}
+
+/// Structure holding the previously decoded frame
+#[derive(Default, Clone, Copy)]
+pub(super) struct Plausibility {
+    /// Last decoded frame, accepted or not
+    last_frame: Option<LastFrame>,
+    /// Number of decoded frames which were not consistent,
+    /// neither with the previous frame nor with the RTC
+    rejected_frames: u16,
+}
+
+impl Plausibility {
+    /// Return the frame if it is consistent with the previously decoded one
+    /// (exactly one minute later, in UTC to cross the changes of offset),
+    /// or with the current time of the RTC, if any.
+    /// A reconstructed frame (some of its bits taken from the prediction)
+    /// agrees with the previous one by construction: it confirms no frame,
+    /// so only a fully received frame is a reference for the next one.
+    /// In any case, the frame is kept to check the next one.
+    pub fn check(
+        &mut self,
+        frame: Dcf77Frame,
+        reconstructed: bool,
+        rtc: Option<Datetime>,
+    ) -> Option<Dcf77Frame> {
+        let datetime = frame.datetime;
+        let follows_last_frame = self
+            .last_frame
+            .filter(|last_frame| !last_frame.reconstructed)
+            .and_then(|last_frame| {
+                datetime.to_utc(frame.utc_offset)
+                    - last_frame
+                        .frame
+                        .datetime
+                        .to_utc(last_frame.frame.utc_offset)
+            })
+            .map(|diff| diff == 1)
+            .unwrap_or(false);
+        let matches_rtc = rtc
+            .and_then(|rtc| datetime - rtc)
+            .map(|diff| diff.abs() <= RTC_TOLERANCE_MINUTES)
+            .unwrap_or(false);
+        let published = follows_last_frame || matches_rtc;
+
+        self.last_frame = Some(LastFrame {
+            frame,
+            reconstructed,
+            published,
+        });
+
+        if published {
+            Some(frame)
+        } else {
+            self.rejected_frames = self.rejected_frames.saturating_add(1);
+            None
+        }
+    }
+
+    /// Frame expected for the current minute, that is, the previous one
+    /// plus one minute, if the previous one was published: the unknown bits
+    /// are never taken from a frame which was not confirmed.
+    pub fn prediction(&self) -> Option<Dcf77Frame> {
+        self.last_frame
+            .filter(|last_frame| last_frame.published)
+            .map(|last_frame| Dcf77Frame {
+                datetime: last_frame.frame.datetime.add_minutes(1),
+                ..last_frame.frame
+            })
+    }
+
+    /// Number of rejected frames since the boot.
+    pub fn rejected_frames(&self) -> u16 {
+        self.rejected_frames
+    }
+}
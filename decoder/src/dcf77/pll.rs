@@ -0,0 +1,152 @@
+//! Software phase-locked loop, disciplining the rate of the internal timer
+//! (driven by a ceramic resonator, accurate to a few thousandths)
+//! against the second marks of DCF77.
+//!
+//! While the phase detector is locked, the timer of the peaks (or, with the
+//! feature `dcf77-int0`, of the beginnings of the pulses) is compared
+//! to the number of seconds elapsed over a window of about one minute:
+//! the remaining error is integrated into a rate correction,
+//! applied by the timer interrupt (see `timer::set_correction`).
+//! Until the rate is acquired, the windows are shorter, so that
+//! the whole range of the correction can be measured.
+use crate::timer::Timer;
+#[cfg(not(feature = "dcf77-int0"))]
+use crate::timer::POLLED_SAMPLES_PERIOD_MS;
+
+/// Duration of a measurement window, in seconds, once the rate is acquired.
+/// It must remain below the period of the timer.
+const PLL_WINDOW_S: u16 = 64;
+/// Duration of a measurement window, in seconds, until the rate is acquired:
+/// the highest correction (2% of 8 s, 160 ms) remains within MAX_ERROR_MS.
+const PLL_ACQUISITION_WINDOW_S: u16 = 8;
+/// Gain of the loop, as a power of two: only a part of the measured error
+/// is corrected at each window, to smooth the uncertainty of the peaks
+const PLL_GAIN_SHIFT: u8 = 1;
+/// Highest correction, in 1/65536 ms per ms (about 2%)
+const MAX_CORRECTION: i16 = 1311;
+/// Highest error, in ms over a window of PLL_WINDOW_S, for the loop to be locked
+/// (two periods of the polled values)
+#[cfg(not(feature = "dcf77-int0"))]
+const LOCKED_ERROR_MS: i16 = 50;
+/// Highest error, in ms over a window, to be considered as a measurement:
+/// above, the phase of the signal was lost during the window
+/// (or the rate is not acquired yet, over a long window).
+const MAX_ERROR_MS: i16 = 200;
+
+/// Structure of the phase-locked loop
+#[derive(Default, Clone, Copy)]
+pub(super) struct Pll {
+    /// Timer of the peak opening the current measurement window
+    window_start: Option<Timer>,
+    /// Correction of the rate of the timer, in 1/65536 ms per ms
+    correction: i16,
+    /// Error measured over the last window, in ms,
+    /// scaled to a window of PLL_WINDOW_S
+    last_error: Option<i16>,
+    /// Whether the rate is acquired, the error over a window
+    /// of PLL_WINDOW_S being measurable
+    acquired: bool,
+}
+
+impl Pll {
+    /// Record the timer of a peak consistent with the previous ones.
+    /// At the end of a window, update and return the correction of the timer rate.
+    pub fn record_peak(&mut self, timer: Timer) -> Option<i16> {
+        let window_start = match self.window_start {
+            Some(window_start) => window_start,
+            None => {
+                self.window_start = Some(timer);
+                return None;
+            }
+        };
+        let window_s = if self.acquired {
+            PLL_WINDOW_S
+        } else {
+            PLL_ACQUISITION_WINDOW_S
+        };
+        let elapsed = (timer - window_start).0;
+        if elapsed < window_s * 1000 - 500 {
+            return None;
+        }
+        self.window_start = Some(timer);
+        if elapsed > window_s * 1000 + 500 {
+            // Too long to be measured without ambiguity
+            return None;
+        }
+
+        // Positive when the timer runs too fast
+        let error = elapsed as i32 - window_s as i32 * 1000;
+        if error.abs() > MAX_ERROR_MS as i32 {
+            self.last_error = None;
+            self.acquired = false;
+            return None;
+        }
+        let scaled_error = error * PLL_WINDOW_S as i32 / window_s as i32;
+        self.last_error = Some(scaled_error as i16);
+        self.acquired = scaled_error.abs() <= MAX_ERROR_MS as i32;
+        let delta = -(error * 65536 / (window_s as i32 * 1000)) >> PLL_GAIN_SHIFT;
+        self.correction = (self.correction as i32 + delta)
+            .clamp(-MAX_CORRECTION as i32, MAX_CORRECTION as i32) as i16;
+        Some(self.correction)
+    }
+
+    /// Correction of the rate of the timer, in 1/65536 ms per ms.
+    pub fn correction(&self) -> i16 {
+        self.correction
+    }
+
+    /// Error measured over the last window, in ms
+    /// scaled to a window of PLL_WINDOW_S, if any.
+    pub fn last_error(&self) -> Option<i16> {
+        self.last_error
+    }
+
+    /// Tolerance, in ms, on the time of a peak some seconds after the previous one while
+    /// the loop is locked: two polled values (the width of the detection), plus the drift.
+    #[cfg(not(feature = "dcf77-int0"))]
+    pub fn peak_tolerance_ms(&self, seconds: u16) -> Option<i32> {
+        let error = self.last_error?.abs();
+        (error <= LOCKED_ERROR_MS).then(|| {
+            let drift =
+                (error as i32 * seconds as i32 + PLL_WINDOW_S as i32 - 1) / PLL_WINDOW_S as i32;
+            2 * POLLED_SAMPLES_PERIOD_MS as i32 + drift
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timer::POLLED_SAMPLES_PERIOD_MS;
+
+    /// Residual error of the rate of a timer, in ppm, after some seconds of second
+    /// marks timed by a board whose resonator is off by a number of ppm,
+    /// the corrections of the loop being applied as by the timer interrupt.
+    fn residual_ppm(resonator_ppm: i64, seconds: u16) -> i64 {
+        let mut pll = Pll::default();
+        // Timer of the board, with its fraction of ms in 1/65536
+        let (mut timer, mut fraction) = (Timer(1), 0);
+        for _ in 0..seconds {
+            let rate = (1_000_000 + resonator_ppm) * (65536 + pll.correction() as i64) / 1_000_000;
+            fraction += 1000 * rate;
+            timer = timer + Timer((fraction >> 16) as u16);
+            fraction &= 0xffff;
+            // The second marks are found among the polled values.
+            pll.record_peak(Timer(timer.0 - timer.0 % POLLED_SAMPLES_PERIOD_MS));
+        }
+        (1_000_000 + resonator_ppm) * (65536 + pll.correction() as i64) / 65536 - 1_000_000
+    }
+
+    #[test]
+    fn resonator_errors_are_corrected() {
+        for resonator_ppm in [-15_000, -5_000, -500, 0, 3_000, 15_000] {
+            let residual_ppm = residual_ppm(resonator_ppm, 20 * 60);
+            assert!(
+                residual_ppm.abs() <= 500,
+                "{} ppm left out of {} ppm",
+                residual_ppm,
+                resonator_ppm
+            );
+        }
+    }
+}
@@ -1,11 +1,14 @@
 //! Structure and methods related to the polled values
 //! of the pin linked to the DCF77 receiver.
-use super::{Dcf77SignalVariant, WorkflowError, POLLED_SAMPLES_FREQUENCY};
+use super::POLLED_SAMPLES_FREQUENCY;
+#[cfg(not(any(feature = "msf", feature = "wwvb", feature = "jjy")))]
+use super::{Dcf77SignalVariant, WorkflowError};
+use crate::timer::POLLED_SAMPLES_PERIOD_MS;
 
 /// Duration of the history of polled values to keep
 pub const POLLED_SAMPLES_HISTORY_S: u8 = 6;
 /// Number of bytes necessary to store 1 second of data
-const POLLED_SAMPLES_BYTES_PER_S: usize = POLLED_SAMPLES_FREQUENCY / 8;
+pub const POLLED_SAMPLES_BYTES_PER_S: usize = POLLED_SAMPLES_FREQUENCY / 8;
 /// Number of bytes necessary to store all the data
 const POLLED_SAMPLES_BYTES: usize =
     (POLLED_SAMPLES_HISTORY_S as usize) * POLLED_SAMPLES_BYTES_PER_S;
@@ -17,6 +20,38 @@ fn count_ones(byte: u8) -> u8 {
     NIBBLE_LOOKUP[(byte & 0x0F) as usize] + NIBBLE_LOOKUP[(byte >> 4) as usize]
 }
 
+/// Mask of the polled values of the last second, with the samples
+/// between the given bounds, in ms from the beginning of the second, set.
+pub const fn samples_mask(bounds_ms: (u16, u16)) -> [u8; POLLED_SAMPLES_BYTES_PER_S] {
+    let mut mask = [0u8; POLLED_SAMPLES_BYTES_PER_S];
+    let mut ms = bounds_ms.0;
+    while ms < bounds_ms.1 {
+        // The most recent sample ends the second.
+        let index = POLLED_SAMPLES_FREQUENCY - 1 - (ms / POLLED_SAMPLES_PERIOD_MS) as usize;
+        mask[index / 8] |= 1 << (index % 8);
+        ms += POLLED_SAMPLES_PERIOD_MS;
+    }
+    mask
+}
+
+/// Pattern of a typical second of a time signal, correlated with the polled values
+/// to find the phase: the samples expected at the logical low and high levels.
+pub struct PhasePattern {
+    low: [u8; POLLED_SAMPLES_BYTES_PER_S],
+    high: [u8; POLLED_SAMPLES_BYTES_PER_S],
+}
+
+impl PhasePattern {
+    /// Create a pattern from the bounds, in ms from the beginning of the second,
+    /// of the samples expected at the logical low and high levels.
+    pub const fn new(low_ms: (u16, u16), high_ms: (u16, u16)) -> Self {
+        Self {
+            low: samples_mask(low_ms),
+            high: samples_mask(high_ms),
+        }
+    }
+}
+
 /// Storage for the polled values
 #[derive(Clone, Copy)]
 pub(super) struct PolledValues {
@@ -58,9 +93,9 @@ impl PolledValues {
     }
 
     /// Compute the scalar product between the current values
-    /// and a fixed pattern representing a typical expected signal.
-    pub fn scalar_product(&self) -> Option<u8> {
-        if self.samples_count < EXPECTED.len() {
+    /// and a pattern representing a typical expected signal, repeated every second.
+    pub fn scalar_product(&self, pattern: &PhasePattern) -> Option<u8> {
+        if self.samples_count < POLLED_SAMPLES_BYTES {
             return None;
         }
 
@@ -70,25 +105,40 @@ impl PolledValues {
         // possible to distinguish a true or false value during the "logical low" phase:
         // <[1,0,0,0,0], [x,0,0,1,0]> = <[1,0,0,0,0], [x,0,0,0,0]>
         // This "effect" is detrimental to the main goal (finding the best correlation).
-        const EXPECTED: [u8; POLLED_SAMPLES_BYTES] = [
-            0xff, 0xff, 0xff, 0xff, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0xff, 0xff, 0xff, 0xff,
-            0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0xff, 0xff, 0xff,
-            0xff, 0x00,
-        ];
-
+        // The samples expected at the logical high level, if any, are counted as is.
         Some(
             self.value
                 .iter()
-                .zip(EXPECTED)
-                .map(|(actual_value, expected_byte)| count_ones(!actual_value & expected_byte))
+                .zip(pattern.low.iter().cycle())
+                .zip(pattern.high.iter().cycle())
+                .map(|((actual_value, low), high)| {
+                    count_ones(!actual_value & low) + count_ones(actual_value & high)
+                })
                 .sum::<u8>(),
         )
     }
 
+    /// Count the samples of the last second at the logical high level
+    /// among the ones of the mask (see `samples_mask`).
+    /// This function must be called when the phase detector finds the beginning
+    /// of the next second: since its peak is found one sample after the end
+    /// of the second, the most recent sample is skipped.
+    #[cfg(any(feature = "msf", feature = "wwvb", feature = "jjy"))]
+    pub fn count_high(&self, mask: &[u8; POLLED_SAMPLES_BYTES_PER_S]) -> u8 {
+        mask.iter()
+            .enumerate()
+            .map(|(index, mask)| {
+                let value = (self.value[index] >> 1) | (self.value[index + 1] << 7);
+                count_ones(value & mask)
+            })
+            .sum::<u8>()
+    }
+
     /// Identify the last dcf77 bit.
     /// This function must be called at the end of a second,
     /// just before the beginning of the next one
     /// (found thanks to the phase detector).
+    #[cfg(not(any(feature = "msf", feature = "wwvb", feature = "jjy")))]
     pub fn last_dcf77_bit(&self) -> Result<Dcf77SignalVariant, WorkflowError> {
         // At the end of the second, the value contains:
         // - 800ms of data that are expected to be false (and ignored)
@@ -0,0 +1,619 @@
+//! Decode the bits of the DCF77 signal
+use super::time_signal::{Minute, TimeSignal};
+#[cfg(not(any(
+    feature = "dcf77-int0",
+    feature = "msf",
+    feature = "wwvb",
+    feature = "jjy"
+)))]
+use super::{
+    polled_values::{PhasePattern, PolledValues},
+    WorkflowError,
+};
+use crate::{Date, Datetime, DayOfWeek, SummerTimeRule, Time, UtcOffset};
+use core::ops::{Deref, DerefMut};
+use ringbuffer::{ConstGenericRingBuffer, RingBuffer};
+
+/// Logical bits of the signal.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Dcf77SignalVariant {
+    High,
+    Low,
+    MinuteEnd,
+    /// Position marker, every ten seconds (WWVB, JJY)
+    Marker,
+    /// Bits A and B of a second (MSF)
+    Pair(bool, bool),
+    /// Bit that could not be identified
+    Unknown,
+}
+
+impl ufmt::uDisplay for Dcf77SignalVariant {
+    /// Format a bit to display on the serial port.
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        match self {
+            Dcf77SignalVariant::High => f.write_char('#'),
+            Dcf77SignalVariant::Low => f.write_char('_'),
+            Dcf77SignalVariant::MinuteEnd => f.write_char('|'),
+            Dcf77SignalVariant::Marker => f.write_char('M'),
+            Dcf77SignalVariant::Pair(a, b) => {
+                f.write_char((b'0' + 2 * (*a as u8) + *b as u8) as char)
+            }
+            Dcf77SignalVariant::Unknown => f.write_char('?'),
+        }
+    }
+}
+
+/// Maximal number of signals held: a minute end, 59 bits of data,
+/// an extra bit during a leap second and the next minute end.
+const SIGNALS_CAPACITY: usize = 62;
+/// Maximal number of unknown bits in a minute which can be
+/// taken from the prediction
+const MAX_PREDICTED_BITS: usize = 4;
+
+/// Signal, new-type pattern for an array of bits
+#[derive(Default)]
+pub(super) struct Signals(ConstGenericRingBuffer<Dcf77SignalVariant, SIGNALS_CAPACITY>);
+
+impl Deref for Signals {
+    type Target = ConstGenericRingBuffer<Dcf77SignalVariant, SIGNALS_CAPACITY>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Signals {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Signals {
+    /// Maximal number of signals held
+    pub const CAPACITY: usize = SIGNALS_CAPACITY;
+
+    /// Dequeue bits up to the next "minute end".
+    pub fn clear_up_to_first_minute_end(&mut self) {
+        if let Some(first_minute_end) = self
+            .iter()
+            .position(|s| s == &Dcf77SignalVariant::MinuteEnd)
+        {
+            for _ in 0..first_minute_end {
+                self.dequeue();
+            }
+        } else {
+            self.clear();
+        }
+    }
+
+    /// Extract, if possible, the candidate symbols of a full minute,
+    /// enclosed between two "minute ends".
+    /// A minute holds 59 symbols, or 60 symbols when a leap second is inserted.
+    /// The closing "minute end" is kept, as the beginning of the next minute.
+    /// A few unknown symbols are taken from the prediction, if any:
+    /// return the symbols along with true if some of them were predicted.
+    pub fn get_minute<S: TimeSignal>(
+        &mut self,
+        prediction: Option<&Minute>,
+    ) -> Option<(Minute, bool)> {
+        if self.front() != Some(&Dcf77SignalVariant::MinuteEnd) {
+            return None;
+        }
+
+        // Number of symbols between both "minute ends"
+        let minute_len = self
+            .iter()
+            .skip(1)
+            .position(|s| s == &Dcf77SignalVariant::MinuteEnd)?;
+
+        // Dequeue first element MinuteEnd
+        self.dequeue();
+
+        if minute_len != 59 && minute_len != 60 {
+            for _ in 0..minute_len {
+                self.dequeue();
+            }
+            return None;
+        }
+
+        let mut minute = Minute([Dcf77SignalVariant::Unknown; 59]);
+        let mut unknown_bits = 0;
+        for (index, symbol) in minute.0.iter_mut().enumerate() {
+            match self.dequeue() {
+                Some(Dcf77SignalVariant::Unknown) | None => {
+                    unknown_bits += 1;
+                    if let Some(prediction) = prediction {
+                        *symbol = prediction.0[index];
+                    }
+                }
+                Some(known) => {
+                    *symbol = known;
+                }
+            }
+        }
+        let mut valid =
+            unknown_bits == 0 || (unknown_bits <= MAX_PREDICTED_BITS && prediction.is_some());
+
+        // The leap second, if any, is always a low bit, and is announced
+        // during the hour before its insertion.
+        if minute_len == 60
+            && (self.dequeue() == Some(Dcf77SignalVariant::High)
+                || !S::leap_second_announced(&minute))
+        {
+            valid = false;
+        }
+
+        valid.then_some((minute, unknown_bits > 0))
+    }
+}
+
+/// A wrapper for an array made of the 59 bits of a candidate message.
+pub(crate) struct Protocol {
+    pub bits: [bool; 59],
+}
+
+/// Possible decode errors
+#[derive(Clone, Copy)]
+pub enum ProtocolError {
+    BadStartMinute,
+    BadStartOfTime,
+    MinuteChecksum,
+    HourChecksum,
+    DateChecksum,
+    SummerTime,
+    WeekdayValue,
+    MinuteValue,
+    HourValue,
+    DayValue,
+    MonthValue,
+    YearValue,
+    Markers,
+    DayZero,
+    DayBeyondMonth,
+    MonthZero,
+    WeekdayMismatch,
+}
+
+impl ProtocolError {
+    /// Number of variants
+    pub const COUNT: usize = 17;
+
+    /// Index of the variant
+    pub fn index(&self) -> usize {
+        match self {
+            ProtocolError::BadStartMinute => 0,
+            ProtocolError::BadStartOfTime => 1,
+            ProtocolError::MinuteChecksum => 2,
+            ProtocolError::HourChecksum => 3,
+            ProtocolError::DateChecksum => 4,
+            ProtocolError::SummerTime => 5,
+            ProtocolError::WeekdayValue => 6,
+            ProtocolError::MinuteValue => 7,
+            ProtocolError::HourValue => 8,
+            ProtocolError::DayValue => 9,
+            ProtocolError::MonthValue => 10,
+            ProtocolError::YearValue => 11,
+            ProtocolError::Markers => 12,
+            ProtocolError::DayZero => 13,
+            ProtocolError::DayBeyondMonth => 14,
+            ProtocolError::MonthZero => 15,
+            ProtocolError::WeekdayMismatch => 16,
+        }
+    }
+}
+
+impl ufmt::uDisplay for ProtocolError {
+    /// Format a protocol error to display on the serial port.
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        match self {
+            ProtocolError::BadStartMinute => f.write_str("proto:start minute"),
+            ProtocolError::BadStartOfTime => f.write_str("proto:start time"),
+            ProtocolError::MinuteChecksum => f.write_str("proto:minute checksum"),
+            ProtocolError::HourChecksum => f.write_str("proto:hour checksum"),
+            ProtocolError::DateChecksum => f.write_str("proto:date checksum"),
+            ProtocolError::SummerTime => f.write_str("proto:summer time"),
+            ProtocolError::WeekdayValue => f.write_str("proto:weekday value"),
+            ProtocolError::MinuteValue => f.write_str("proto:minute value"),
+            ProtocolError::HourValue => f.write_str("proto:hour value"),
+            ProtocolError::DayValue => f.write_str("proto:day value"),
+            ProtocolError::MonthValue => f.write_str("proto:month value"),
+            ProtocolError::YearValue => f.write_str("proto:year value"),
+            ProtocolError::Markers => f.write_str("proto:markers"),
+            ProtocolError::DayZero => f.write_str("proto:day zero"),
+            ProtocolError::DayBeyondMonth => f.write_str("proto:day beyond month"),
+            ProtocolError::MonthZero => f.write_str("proto:month zero"),
+            ProtocolError::WeekdayMismatch => f.write_str("proto:weekday mismatch"),
+        }
+    }
+}
+
+impl Protocol {
+    pub const START_MINUTE: usize = 0;
+    pub const CALL_BIT: usize = 15;
+    pub const SUMMER_TIME_ANNOUNCEMENT: usize = 16;
+    pub const CEST: usize = 17;
+    pub const CET: usize = 18;
+    pub const LEAP_SECOND_ANNOUNCEMENT: usize = 19;
+    pub const START_OF_TIME: usize = 20;
+    pub const MINUTE_LSB: usize = 21;
+    pub const MINUTE_MSB: usize = 27;
+    pub const MINUTE_PARITY: usize = 28;
+    pub const HOUR_LSB: usize = 29;
+    pub const HOUR_MSB: usize = 34;
+    pub const HOUR_PARITY: usize = 35;
+    pub const DAY_OF_MONTH_LSB: usize = 36;
+    pub const DAY_OF_MONTH_MSB: usize = 41;
+    pub const DAY_OF_WEEK_LSB: usize = 42;
+    pub const DAY_OF_WEEK_MSB: usize = 44;
+    pub const MONTH_LSB: usize = 45;
+    pub const MONTH_MSB: usize = 49;
+    pub const YEAR_LSB: usize = 50;
+    pub const YEAR_MSB: usize = 57;
+    pub const DATE_PARITY: usize = 58;
+    pub const DATE_LSB: usize = Protocol::DAY_OF_MONTH_LSB;
+    pub const DATE_MSB: usize = Protocol::YEAR_MSB;
+}
+
+/// Flags transmitted along with the datetime
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub struct Dcf77Flags {
+    /// Call bit, used by the emitter to signal irregularities
+    pub call_bit: bool,
+    /// A change between CET and CEST occurs at the end of this hour
+    pub summer_time_announcement: bool,
+    /// A leap second is inserted at the end of this hour
+    pub leap_second_announcement: bool,
+}
+
+/// Decoded frame of a full minute: the local datetime, its offset to UTC and the flags
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Dcf77Frame {
+    pub datetime: Datetime,
+    pub utc_offset: UtcOffset,
+    pub flags: Dcf77Flags,
+}
+
+impl Dcf77Frame {
+    /// Frame transmitted during the minute of a local datetime, that is,
+    /// the frame of the following minute, with the offset given by the rule
+    /// of summer time, whose changes are announced during the preceding hour.
+    pub fn transmitted_during(
+        datetime: &Datetime,
+        utc_offset: UtcOffset,
+        rule: &SummerTimeRule,
+    ) -> Self {
+        let utc = datetime.to_utc(utc_offset).add_minutes(1);
+        let utc_offset = rule.utc_offset(&utc);
+        let next_hour = Datetime {
+            time: Time {
+                minute: 0,
+                ..utc.time
+            },
+            ..utc
+        }
+        .add_minutes(60);
+        Dcf77Frame {
+            datetime: Datetime {
+                time: Time {
+                    second: Some(0),
+                    ..utc.time
+                },
+                ..utc
+            }
+            .to_local(utc_offset),
+            utc_offset,
+            flags: Dcf77Flags {
+                call_bit: false,
+                summer_time_announcement: rule.utc_offset(&next_hour) != utc_offset,
+                leap_second_announcement: false,
+            },
+        }
+    }
+}
+
+impl TryFrom<Protocol> for Dcf77Frame {
+    type Error = ProtocolError;
+
+    /// Try to convert the bits of a protocol into a frame
+    fn try_from(protocol: Protocol) -> Result<Self, Self::Error> {
+        let flags = Dcf77Flags {
+            call_bit: protocol.bits[Protocol::CALL_BIT],
+            summer_time_announcement: protocol.bits[Protocol::SUMMER_TIME_ANNOUNCEMENT],
+            leap_second_announcement: protocol.bits[Protocol::LEAP_SECOND_ANNOUNCEMENT],
+        };
+
+        // The consistency of both bits is checked during the conversion to datetime
+        let utc_offset = if protocol.bits[Protocol::CEST] {
+            UtcOffset::CEST
+        } else {
+            UtcOffset::CET
+        };
+
+        Ok(Dcf77Frame {
+            datetime: Datetime::try_from(protocol)?,
+            utc_offset,
+            flags,
+        })
+    }
+}
+
+impl TryFrom<Protocol> for Datetime {
+    type Error = ProtocolError;
+
+    /// Try to convert the bits of a protocol into a datetime object
+    fn try_from(protocol: Protocol) -> Result<Self, Self::Error> {
+        // Check fixed values
+        if protocol.bits[Protocol::START_MINUTE] {
+            return Err(ProtocolError::BadStartMinute);
+        }
+
+        if !protocol.bits[Protocol::START_OF_TIME] {
+            return Err(ProtocolError::BadStartOfTime);
+        }
+
+        // Check parity
+        if !check_even_parity(
+            &protocol.bits[Protocol::MINUTE_LSB..=Protocol::MINUTE_MSB],
+            protocol.bits[Protocol::MINUTE_PARITY],
+        ) {
+            return Err(ProtocolError::MinuteChecksum);
+        };
+        if !check_even_parity(
+            &protocol.bits[Protocol::HOUR_LSB..=Protocol::HOUR_MSB],
+            protocol.bits[Protocol::HOUR_PARITY],
+        ) {
+            return Err(ProtocolError::HourChecksum);
+        };
+        if !check_even_parity(
+            &protocol.bits[Protocol::DATE_LSB..=Protocol::DATE_MSB],
+            protocol.bits[Protocol::DATE_PARITY],
+        ) {
+            return Err(ProtocolError::DateChecksum);
+        };
+
+        // Check CEST/CET consistency
+        if !(protocol.bits[Protocol::CEST] ^ protocol.bits[Protocol::CET]) {
+            return Err(ProtocolError::SummerTime);
+        }
+
+        let weekday =
+            bits_slice_to_u8(&protocol.bits[Protocol::DAY_OF_WEEK_LSB..=Protocol::DAY_OF_WEEK_MSB]);
+        let weekday = match weekday {
+            1 => DayOfWeek::Monday,
+            2 => DayOfWeek::Tuesday,
+            3 => DayOfWeek::Wednesday,
+            4 => DayOfWeek::Thursday,
+            5 => DayOfWeek::Friday,
+            6 => DayOfWeek::Saturday,
+            7 => DayOfWeek::Sunday,
+            _ => return Err(ProtocolError::WeekdayValue),
+        };
+
+        let minute = bits_slice_to_u8(&protocol.bits[Protocol::MINUTE_LSB..=Protocol::MINUTE_MSB]);
+        if minute > 59 {
+            return Err(ProtocolError::MinuteValue);
+        }
+
+        let hour = bits_slice_to_u8(&protocol.bits[Protocol::HOUR_LSB..=Protocol::HOUR_MSB]);
+        if hour > 23 {
+            return Err(ProtocolError::HourValue);
+        }
+
+        let day = bits_slice_to_u8(
+            &protocol.bits[Protocol::DAY_OF_MONTH_LSB..=Protocol::DAY_OF_MONTH_MSB],
+        );
+        if day > 31 {
+            return Err(ProtocolError::DayValue);
+        }
+        let month = bits_slice_to_u8(&protocol.bits[Protocol::MONTH_LSB..=Protocol::MONTH_MSB]);
+        if month > 12 {
+            return Err(ProtocolError::MonthValue);
+        }
+
+        let year = bits_slice_to_u8(&protocol.bits[Protocol::YEAR_LSB..=Protocol::YEAR_MSB]);
+        if year > 99 {
+            return Err(ProtocolError::YearValue);
+        }
+
+        let date = Date {
+            year,
+            month,
+            day,
+            weekday,
+        };
+        check_calendar(&date)?;
+
+        Ok(Datetime {
+            date,
+            time: Time {
+                hour,
+                minute,
+                second: None,
+            },
+        })
+    }
+}
+
+impl From<&Dcf77Frame> for Protocol {
+    /// Encode a frame into the bits of a protocol,
+    /// the bits of the civil warnings being left to zero.
+    fn from(frame: &Dcf77Frame) -> Self {
+        let mut bits = [false; 59];
+        let datetime = &frame.datetime;
+
+        bits[Protocol::CALL_BIT] = frame.flags.call_bit;
+        bits[Protocol::SUMMER_TIME_ANNOUNCEMENT] = frame.flags.summer_time_announcement;
+        bits[Protocol::CEST] = frame.utc_offset == UtcOffset::CEST;
+        bits[Protocol::CET] = frame.utc_offset != UtcOffset::CEST;
+        bits[Protocol::LEAP_SECOND_ANNOUNCEMENT] = frame.flags.leap_second_announcement;
+        bits[Protocol::START_OF_TIME] = true;
+
+        u8_to_bits_slice(
+            datetime.time.minute,
+            &mut bits[Protocol::MINUTE_LSB..=Protocol::MINUTE_MSB],
+        );
+        bits[Protocol::MINUTE_PARITY] =
+            even_parity(&bits[Protocol::MINUTE_LSB..=Protocol::MINUTE_MSB]);
+
+        u8_to_bits_slice(
+            datetime.time.hour,
+            &mut bits[Protocol::HOUR_LSB..=Protocol::HOUR_MSB],
+        );
+        bits[Protocol::HOUR_PARITY] = even_parity(&bits[Protocol::HOUR_LSB..=Protocol::HOUR_MSB]);
+
+        u8_to_bits_slice(
+            datetime.date.day,
+            &mut bits[Protocol::DAY_OF_MONTH_LSB..=Protocol::DAY_OF_MONTH_MSB],
+        );
+        u8_to_bits_slice(
+            datetime.date.weekday as u8,
+            &mut bits[Protocol::DAY_OF_WEEK_LSB..=Protocol::DAY_OF_WEEK_MSB],
+        );
+        u8_to_bits_slice(
+            datetime.date.month,
+            &mut bits[Protocol::MONTH_LSB..=Protocol::MONTH_MSB],
+        );
+        u8_to_bits_slice(
+            datetime.date.year,
+            &mut bits[Protocol::YEAR_LSB..=Protocol::YEAR_MSB],
+        );
+        bits[Protocol::DATE_PARITY] = even_parity(&bits[Protocol::DATE_LSB..=Protocol::DATE_MSB]);
+
+        Protocol { bits }
+    }
+}
+
+impl From<&Minute> for Protocol {
+    /// Take the bits of the symbols of a minute.
+    fn from(minute: &Minute) -> Self {
+        let mut bits = [false; 59];
+        for (bit, symbol) in bits.iter_mut().zip(minute.0) {
+            *bit = symbol == Dcf77SignalVariant::High;
+        }
+        Protocol { bits }
+    }
+}
+
+impl From<&Protocol> for Minute {
+    /// Take the symbols of the bits of a protocol.
+    fn from(protocol: &Protocol) -> Self {
+        let mut minute = Minute([Dcf77SignalVariant::Low; 59]);
+        for (symbol, &bit) in minute.0.iter_mut().zip(protocol.bits.iter()) {
+            if bit {
+                *symbol = Dcf77SignalVariant::High;
+            }
+        }
+        minute
+    }
+}
+
+/// DCF77, 77.5 kHz, Mainflingen (Germany): the carrier is reduced
+/// during 100 ms (low bit) or 200 ms (high bit) at the beginning of each second,
+/// except at the last second of the minute.
+/// The frame is the one of the minute which begins.
+#[cfg(not(any(feature = "msf", feature = "wwvb", feature = "jjy")))]
+pub(super) struct Dcf77Signal;
+
+#[cfg(not(any(feature = "msf", feature = "wwvb", feature = "jjy")))]
+impl TimeSignal for Dcf77Signal {
+    /// The carrier is never reduced after the first 200 ms of a second.
+    #[cfg(not(feature = "dcf77-int0"))]
+    const PHASE_PATTERN: PhasePattern = PhasePattern::new((200, 1000), (0, 0));
+
+    #[cfg(not(feature = "dcf77-int0"))]
+    fn classify(polled_values: &PolledValues) -> Result<Dcf77SignalVariant, WorkflowError> {
+        polled_values.last_dcf77_bit()
+    }
+
+    fn leap_second_announced(minute: &Minute) -> bool {
+        minute.0[Protocol::LEAP_SECOND_ANNOUNCEMENT] == Dcf77SignalVariant::High
+    }
+
+    fn decode(minute: &Minute) -> Result<Dcf77Frame, ProtocolError> {
+        Dcf77Frame::try_from(Protocol::from(minute))
+    }
+
+    fn encode(frame: &Dcf77Frame) -> Option<Minute> {
+        Some(Minute::from(&Protocol::from(frame)))
+    }
+
+    /// The frame of the following minute, the carrier not being reduced
+    /// during the last second.
+    #[cfg(feature = "generator")]
+    fn transmitted_during(
+        datetime: &Datetime,
+        utc_offset: UtcOffset,
+        rule: &SummerTimeRule,
+    ) -> [Dcf77SignalVariant; 60] {
+        let frame = Dcf77Frame::transmitted_during(datetime, utc_offset, rule);
+        let mut symbols = [Dcf77SignalVariant::MinuteEnd; 60];
+        symbols[..59].copy_from_slice(&Minute::from(&Protocol::from(&frame)).0);
+        symbols
+    }
+
+    /// The polled value is high while the carrier is reduced.
+    #[cfg(feature = "generator")]
+    fn high_levels(symbol: Dcf77SignalVariant) -> &'static [(u16, u16)] {
+        match symbol {
+            Dcf77SignalVariant::High => &[(0, 200)],
+            Dcf77SignalVariant::Low => &[(0, 100)],
+            _ => &[],
+        }
+    }
+}
+
+/// Check a date against the calendar: the month and the day
+/// within its length (leap years included), and the day of the week.
+/// The fields are expected to be within their ranges of values.
+pub(super) fn check_calendar(date: &Date) -> Result<(), ProtocolError> {
+    if date.month == 0 {
+        return Err(ProtocolError::MonthZero);
+    }
+    if date.day == 0 {
+        return Err(ProtocolError::DayZero);
+    }
+    if date.day > date.days_in_month() {
+        return Err(ProtocolError::DayBeyondMonth);
+    }
+    if date.weekday != date.computed_weekday() {
+        return Err(ProtocolError::WeekdayMismatch);
+    }
+    Ok(())
+}
+
+/// Compute the bit making the parity of a slice of the bits even
+pub(super) fn even_parity(bits: &[bool]) -> bool {
+    bits.iter().fold(false, |acc, value| acc ^ value)
+}
+
+/// Check the parity of a slice of the bits
+pub(super) fn check_even_parity(bits: &[bool], checksum: bool) -> bool {
+    !bits.iter().fold(checksum, |acc, value| (acc ^ value))
+}
+
+/// Convert a slice of bits to a number according to
+/// the pseudo-BCD encoding used in the protocol.
+fn bits_slice_to_u8(bits: &[bool]) -> u8 {
+    [1, 2, 4, 8, 10, 20, 40, 80]
+        .iter()
+        .zip(bits)
+        .flat_map(|(&weight, &bit)| if bit { Some(weight) } else { None })
+        .sum()
+}
+
+/// Convert a number to a slice of bits according to
+/// the pseudo-BCD encoding used in the protocol.
+fn u8_to_bits_slice(value: u8, bits: &mut [bool]) {
+    // The weights of the bits are the ones of the packed BCD
+    let bcd = ((value / 10) << 4) | (value % 10);
+    for (index, bit) in bits.iter_mut().enumerate() {
+        *bit = bcd & (1 << index) != 0;
+    }
+}
@@ -0,0 +1,119 @@
+//! Decoding of the DCF77 bits from the widths of the pulses, when the edges
+//! of the input are timestamped by the external interrupt INT0
+//! (feature `dcf77-int0`) instead of being polled:
+//! a pulse of 100 ms is a low bit, a pulse of 200 ms a high bit,
+//! and a missing pulse (two seconds between pulses) the end of a minute.
+//!
+//! The bit of a pulse is kept pending until the next pulse begins,
+//! so that a short drop of the signal does not split a pulse in two.
+use super::{Dcf77SignalVariant, WorkflowError};
+use crate::timer::Timer;
+
+/// Shortest pulse, in ms: below, it is a glitch
+const MIN_PULSE_MS: u16 = 40;
+/// Longest pulse of a low bit, in ms
+const MAX_LOW_PULSE_MS: u16 = 140;
+/// Longest pulse of a high bit, in ms
+const MAX_HIGH_PULSE_MS: u16 = 260;
+/// Longest drop of the signal within a pulse, in ms
+const MAX_GLITCH_MS: u16 = 30;
+/// Acceptance window of the spacing between pulses, in ms
+const PULSE_SPACING_MS: (u16, u16) = (900, 1100);
+/// Acceptance window of the spacing between pulses around the end of a minute, in ms
+const MINUTE_END_SPACING_MS: (u16, u16) = (1900, 2100);
+
+/// Decoder of the pulses
+#[derive(Default)]
+pub(super) struct PulseDecoder {
+    /// Timer of the last rising edge, beginning of a pulse or of a glitch
+    rise: Option<Timer>,
+    /// Beginning and end of the last valid pulse
+    last_pulse: Option<(Timer, Timer)>,
+    /// Bit of the last valid pulse, not yet returned
+    pending_bit: Option<Result<Dcf77SignalVariant, WorkflowError>>,
+    /// The end of a minute follows the last returned bit
+    minute_end_queued: bool,
+    /// Beginning of the last pulse in phase with the previous one,
+    /// not yet taken by the phase-locked loop
+    second_mark: Option<Timer>,
+}
+
+impl PulseDecoder {
+    /// Process a new edge of the signal, if any, with its timer and the level after it.
+    /// Return the bit of the previous pulse once the current one is complete,
+    /// followed, at the next call, by the end of a minute if a pulse was missing.
+    pub fn process_edge(
+        &mut self,
+        edge: Option<(Timer, bool)>,
+    ) -> Result<Option<Dcf77SignalVariant>, WorkflowError> {
+        if self.minute_end_queued {
+            self.minute_end_queued = false;
+            return Ok(Some(Dcf77SignalVariant::MinuteEnd));
+        }
+        let (timer, level) = match edge {
+            Some(edge) => edge,
+            None => return Ok(None),
+        };
+        if level {
+            self.rise = Some(timer);
+            return Ok(None);
+        }
+        let rise = match self.rise.take() {
+            Some(rise) => rise,
+            None => return Ok(None),
+        };
+
+        if let Some((start, end)) = self.last_pulse {
+            if rise - end < Timer(MAX_GLITCH_MS) && timer - start <= Timer(MAX_HIGH_PULSE_MS) {
+                // The signal dropped only for a glitch: this is the same pulse.
+                self.last_pulse = Some((start, timer));
+                self.pending_bit = Some(Self::classify(timer - start));
+                return Ok(None);
+            }
+        }
+        let width = timer - rise;
+        if width < Timer(MIN_PULSE_MS) {
+            // Glitch, ignored
+            return Ok(None);
+        }
+
+        // A new pulse: the previous one is complete.
+        let last_pulse = self.last_pulse.replace((rise, timer));
+        let bit = self.pending_bit.replace(Self::classify(width));
+        let (last_start, _) = match last_pulse {
+            Some(last_pulse) => last_pulse,
+            None => return Ok(None),
+        };
+        let diff = rise - last_start;
+        if diff < Timer(PULSE_SPACING_MS.0) {
+            // Spurious pulse: ignore it, keeping the previous one as reference.
+            self.last_pulse = last_pulse;
+            self.pending_bit = bit;
+            return Err(WorkflowError::LastPeakTooClose(diff));
+        }
+        if diff >= Timer(MINUTE_END_SPACING_MS.0) && diff <= Timer(MINUTE_END_SPACING_MS.1) {
+            self.minute_end_queued = true;
+        } else if diff > Timer(PULSE_SPACING_MS.1) {
+            // Some pulses were missed.
+            return Err(WorkflowError::LastPeakTooFar(diff));
+        }
+        self.second_mark = Some(rise);
+        bit.transpose()
+    }
+
+    /// Beginning of the last pulse in phase with the previous one, if any since the last call.
+    pub fn take_second_mark(&mut self) -> Option<Timer> {
+        self.second_mark.take()
+    }
+
+    /// Identify the bit from the width of the pulse.
+    fn classify(width: Timer) -> Result<Dcf77SignalVariant, WorkflowError> {
+        if width <= Timer(MAX_LOW_PULSE_MS) {
+            Ok(Dcf77SignalVariant::Low)
+        } else if width <= Timer(MAX_HIGH_PULSE_MS) {
+            Ok(Dcf77SignalVariant::High)
+        } else {
+            Err(WorkflowError::BadPulseWidth(width))
+        }
+    }
+}
@@ -0,0 +1,292 @@
+//! Time signals handled by the decoder: DCF77 by default,
+//! or one of MSF, WWVB and JJY, selected by a cargo feature.
+//!
+//! Each signal defines the pattern of its seconds (to find their phase),
+//! the classification of a second into a symbol and the decoding of the
+//! symbols of a full minute; the rest of the decoding is common.
+#[cfg(any(feature = "wwvb", feature = "jjy"))]
+use super::polled_values::{samples_mask, POLLED_SAMPLES_BYTES_PER_S};
+#[cfg(not(feature = "dcf77-int0"))]
+use super::{
+    polled_values::{PhasePattern, PolledValues},
+    WorkflowError,
+};
+use super::{protocol::Dcf77Frame, Dcf77SignalVariant, ProtocolError};
+#[cfg(any(feature = "wwvb", feature = "jjy"))]
+use crate::{Date, DayOfWeek};
+#[cfg(feature = "generator")]
+use crate::{Datetime, SummerTimeRule, UtcOffset};
+
+#[cfg(feature = "jjy")]
+mod jjy;
+#[cfg(feature = "msf")]
+mod msf;
+#[cfg(feature = "wwvb")]
+mod wwvb;
+
+#[cfg(any(
+    all(feature = "msf", feature = "wwvb"),
+    all(feature = "msf", feature = "jjy"),
+    all(feature = "wwvb", feature = "jjy")
+))]
+compile_error!("Only one of the features `msf`, `wwvb` and `jjy` can be enabled");
+#[cfg(all(
+    feature = "dcf77-int0",
+    any(feature = "msf", feature = "wwvb", feature = "jjy")
+))]
+compile_error!("The feature `dcf77-int0` decodes the pulses of DCF77 only");
+#[cfg(all(
+    feature = "dcf77-tx",
+    any(feature = "msf", feature = "wwvb", feature = "jjy")
+))]
+compile_error!("The feature `dcf77-tx` re-broadcasts DCF77 only, not the received signal");
+
+/// Time signal decoded, selected by the cargo features
+#[cfg(not(any(feature = "msf", feature = "wwvb", feature = "jjy")))]
+pub(super) type SelectedSignal = super::protocol::Dcf77Signal;
+/// Time signal decoded, selected by the cargo features
+#[cfg(feature = "msf")]
+pub(super) type SelectedSignal = msf::Msf;
+/// Time signal decoded, selected by the cargo features
+#[cfg(feature = "wwvb")]
+pub(super) type SelectedSignal = wwvb::Wwvb;
+/// Time signal decoded, selected by the cargo features
+#[cfg(feature = "jjy")]
+pub(super) type SelectedSignal = jjy::Jjy;
+
+/// Seconds of the position markers (WWVB, JJY); the last one
+/// is followed by the marker beginning the next minute.
+#[cfg(any(feature = "wwvb", feature = "jjy"))]
+const POSITION_MARKERS: [usize; 6] = [9, 19, 29, 39, 49, 59];
+/// Samples within any pulse, margins excluded (WWVB, JJY)
+#[cfg(any(feature = "wwvb", feature = "jjy"))]
+const PULSE_BEGINNING: [u8; POLLED_SAMPLES_BYTES_PER_S] = samples_mask((50, 150));
+/// Samples within a pulse of 500 ms, but not of 200 ms, margins excluded (WWVB, JJY)
+#[cfg(any(feature = "wwvb", feature = "jjy"))]
+const PULSE_500_MS: [u8; POLLED_SAMPLES_BYTES_PER_S] = samples_mask((250, 450));
+/// Samples within a pulse of 800 ms, but not of 500 ms, margins excluded (WWVB, JJY)
+#[cfg(any(feature = "wwvb", feature = "jjy"))]
+const PULSE_800_MS: [u8; POLLED_SAMPLES_BYTES_PER_S] = samples_mask((550, 750));
+
+/// Symbols of a full minute, enclosed between two "minute ends"
+/// (a leap second excluded).
+#[derive(Clone, Copy)]
+pub(crate) struct Minute(pub [Dcf77SignalVariant; 59]);
+
+#[cfg(all(test, any(feature = "wwvb", feature = "jjy")))]
+impl Minute {
+    /// Minute of the symbols of the seconds 1 to 59, written as `0` and `1`
+    /// for the bits and `M` for the position markers, whitespace ignored.
+    fn from_symbols(symbols: &str) -> Self {
+        let mut minute = Minute([Dcf77SignalVariant::Unknown; 59]);
+        let symbols = symbols.chars().filter(|c| !c.is_whitespace());
+        for (second, symbol) in minute.0.iter_mut().zip(symbols) {
+            *second = match symbol {
+                '0' => Dcf77SignalVariant::Low,
+                '1' => Dcf77SignalVariant::High,
+                _ => Dcf77SignalVariant::Marker,
+            };
+        }
+        minute
+    }
+}
+
+/// Decoder of a time signal
+pub(super) trait TimeSignal {
+    /// Pattern of a typical second, to find the phase of the signal.
+    /// The edge beginning the second may be enough: every sample
+    /// of the pattern adds the noise of the signal to the correlation.
+    #[cfg(not(feature = "dcf77-int0"))]
+    const PHASE_PATTERN: PhasePattern;
+
+    /// Identify the symbol of the second which just ended.
+    /// This function must be called at the end of a second,
+    /// just before the beginning of the next one
+    /// (found thanks to the phase detector).
+    #[cfg(not(feature = "dcf77-int0"))]
+    fn classify(polled_values: &PolledValues) -> Result<Dcf77SignalVariant, WorkflowError>;
+
+    /// Symbol to record, given the previous one: a symbol may only be
+    /// identified as the beginning of a minute in its context.
+    fn follow(
+        _previous: Option<Dcf77SignalVariant>,
+        symbol: Dcf77SignalVariant,
+    ) -> Dcf77SignalVariant {
+        symbol
+    }
+
+    /// Return true if a leap second, an extra second at the end of the minute,
+    /// is announced in the symbols of the minute.
+    fn leap_second_announced(_minute: &Minute) -> bool {
+        false
+    }
+
+    /// Try to decode the symbols of a full minute into a frame
+    /// of the minute which begins.
+    fn decode(minute: &Minute) -> Result<Dcf77Frame, ProtocolError>;
+
+    /// Encode a frame into the symbols of a minute, if supported,
+    /// to predict the symbols which could not be identified.
+    fn encode(_frame: &Dcf77Frame) -> Option<Minute> {
+        None
+    }
+
+    /// Symbols transmitted during the minute of a local datetime, from its second 0,
+    /// the local time following the rule of summer time (see `CaptureGenerator`).
+    #[cfg(feature = "generator")]
+    fn transmitted_during(
+        datetime: &Datetime,
+        utc_offset: UtcOffset,
+        rule: &SummerTimeRule,
+    ) -> [Dcf77SignalVariant; 60];
+
+    /// Intervals of a second, in ms from its beginning, during which
+    /// the polled value is high when a symbol is transmitted.
+    #[cfg(feature = "generator")]
+    fn high_levels(symbol: Dcf77SignalVariant) -> &'static [(u16, u16)];
+}
+
+/// Field of a frame: the second of its first bit and the weights of its bits,
+/// in the order of transmission (the unused bits weighing zero)
+#[cfg(any(feature = "msf", feature = "wwvb", feature = "jjy"))]
+struct Field {
+    second: usize,
+    weights: &'static [u16],
+}
+
+#[cfg(any(feature = "msf", feature = "wwvb", feature = "jjy"))]
+impl Field {
+    /// Bits of the field, among the bits of a minute indexed by their second.
+    fn bits<'a>(&self, bits: &'a [bool]) -> &'a [bool] {
+        &bits[self.second..self.second + self.weights.len()]
+    }
+
+    /// Value of the field, the sum of the weights of the bits set.
+    fn value(&self, bits: &[bool]) -> u16 {
+        self.weights
+            .iter()
+            .zip(self.bits(bits))
+            .flat_map(|(&weight, &bit)| if bit { Some(weight) } else { None })
+            .sum()
+    }
+
+    /// Set the bits of the field to a value: the weights of each digit
+    /// decreasing, the heaviest ones are taken first.
+    #[cfg(feature = "generator")]
+    fn set(&self, bits: &mut [bool], mut value: u16) {
+        for (&weight, bit) in self.weights.iter().zip(&mut bits[self.second..]) {
+            *bit = weight != 0 && value >= weight;
+            if *bit {
+                value -= weight;
+            }
+        }
+    }
+}
+
+/// Date of a day of the year (1 for January 1st), if valid.
+/// Years are within 2000-2099, so every fourth year is a leap year.
+#[cfg(any(feature = "wwvb", feature = "jjy"))]
+fn date_from_day_of_year(year: u8, day_of_year: u16) -> Option<Date> {
+    let mut date = Date {
+        day: 1,
+        month: 1,
+        year,
+        weekday: DayOfWeek::Monday,
+    };
+    let mut day_of_year = day_of_year.checked_sub(1)?;
+    while day_of_year >= date.days_in_month() as u16 {
+        day_of_year -= date.days_in_month() as u16;
+        date.month += 1;
+        if date.month > 12 {
+            return None;
+        }
+    }
+    date.day += day_of_year as u8;
+    date.weekday = date.computed_weekday();
+    Some(date)
+}
+
+/// Width, in ms, of the pulse beginning the second which just ended
+/// (WWVB, JJY): 200, 500 or 800 ms.
+#[cfg(any(feature = "wwvb", feature = "jjy"))]
+fn pulse_width(polled_values: &PolledValues) -> Result<u16, WorkflowError> {
+    // A second without any pulse (missed by the receiver) is not a bit.
+    let count = polled_values.count_high(&PULSE_BEGINNING);
+    if count < 3 {
+        return Err(WorkflowError::BadBit(count));
+    }
+    // 200 ms = 8 polled bits at logical true, over each part of the pulse
+    let part = |count| match count {
+        0..=2 => Ok(false),
+        6..=8 => Ok(true),
+        _ => Err(WorkflowError::BadBit(count)),
+    };
+    match (
+        part(polled_values.count_high(&PULSE_500_MS))?,
+        part(polled_values.count_high(&PULSE_800_MS))?,
+    ) {
+        (false, false) => Ok(200),
+        (true, false) => Ok(500),
+        (true, true) => Ok(800),
+        (false, true) => Err(WorkflowError::BadBit(0)),
+    }
+}
+
+/// Identify the beginning of a minute (WWVB, JJY):
+/// the second of two consecutive position markers.
+#[cfg(any(feature = "wwvb", feature = "jjy"))]
+fn follow_markers(
+    previous: Option<Dcf77SignalVariant>,
+    symbol: Dcf77SignalVariant,
+) -> Dcf77SignalVariant {
+    if previous == Some(Dcf77SignalVariant::Marker) && symbol == Dcf77SignalVariant::Marker {
+        Dcf77SignalVariant::MinuteEnd
+    } else {
+        symbol
+    }
+}
+
+/// Bits of a minute with position markers (WWVB, JJY), indexed by their second,
+/// from 1 (after the marker beginning the minute) to 59, once the markers are checked.
+#[cfg(any(feature = "wwvb", feature = "jjy"))]
+fn marked_bits(minute: &Minute) -> Result<[bool; 60], ProtocolError> {
+    let mut bits = [false; 60];
+    for (second, symbol) in (1..).zip(minute.0) {
+        match symbol {
+            Dcf77SignalVariant::Marker if POSITION_MARKERS.contains(&second) => (),
+            Dcf77SignalVariant::High if !POSITION_MARKERS.contains(&second) => bits[second] = true,
+            Dcf77SignalVariant::Low if !POSITION_MARKERS.contains(&second) => (),
+            _ => return Err(ProtocolError::Markers),
+        }
+    }
+    Ok(bits)
+}
+
+/// Day of the year of a date (1 for January 1st), the inverse
+/// of `date_from_day_of_year`.
+#[cfg(all(feature = "generator", any(feature = "wwvb", feature = "jjy")))]
+fn day_of_year(date: &Date) -> u16 {
+    let january_1st = Date {
+        day: 1,
+        month: 1,
+        ..*date
+    };
+    (*date - january_1st) as u16 + 1
+}
+
+/// Symbols of the bits of a minute with position markers (WWVB, JJY),
+/// indexed by their second, the markers included: the inverse of `marked_bits`.
+#[cfg(all(feature = "generator", any(feature = "wwvb", feature = "jjy")))]
+fn marked_symbols(bits: &[bool; 60]) -> [Dcf77SignalVariant; 60] {
+    let mut symbols = [Dcf77SignalVariant::Marker; 60];
+    for (second, symbol) in symbols.iter_mut().enumerate() {
+        if second != 0 && !POSITION_MARKERS.contains(&second) {
+            *symbol = if bits[second] {
+                Dcf77SignalVariant::High
+            } else {
+                Dcf77SignalVariant::Low
+            };
+        }
+    }
+    symbols
+}
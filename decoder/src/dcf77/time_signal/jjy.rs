@@ -0,0 +1,232 @@
+//! JJY, 40 kHz (Mount Otakadoya) or 60 kHz (Mount Hagane), Japan:
+//! the carrier is on at the beginning of each second during 800 ms (bit 0),
+//! 500 ms (bit 1) or 200 ms (position marker, every ten seconds and at
+//! the beginning of the minute); the logical high level is the carrier on.
+//! The bits hold the Japan Standard Time of the current minute (in BCD, MSB first).
+//! At the minutes 15 and 45, the call sign replaces the year and the day of the week,
+//! so that these frames are usually rejected by the checks.
+use super::{
+    date_from_day_of_year, follow_markers, marked_bits, pulse_width, Field, Minute, TimeSignal,
+};
+#[cfg(feature = "generator")]
+use super::{day_of_year, marked_symbols};
+#[cfg(feature = "generator")]
+use crate::{dcf77::protocol::even_parity, SummerTimeRule};
+use crate::{
+    dcf77::{
+        polled_values::{PhasePattern, PolledValues},
+        protocol::{check_even_parity, Dcf77Flags, Dcf77Frame, ProtocolError},
+        Dcf77SignalVariant, WorkflowError,
+    },
+    Datetime, DayOfWeek, Time, UtcOffset,
+};
+
+/// Fields of the bits
+const MINUTE: Field = Field {
+    second: 1,
+    weights: &[40, 20, 10, 0, 8, 4, 2, 1],
+};
+const HOUR: Field = Field {
+    second: 12,
+    weights: &[20, 10, 0, 8, 4, 2, 1],
+};
+const DAY_OF_YEAR: Field = Field {
+    second: 22,
+    weights: &[200, 100, 0, 80, 40, 20, 10, 0, 8, 4, 2, 1],
+};
+const YEAR: Field = Field {
+    second: 41,
+    weights: &[80, 40, 20, 10, 8, 4, 2, 1],
+};
+const DAY_OF_WEEK: Field = Field {
+    second: 50,
+    weights: &[4, 2, 1],
+};
+/// Seconds of the even parities
+const HOUR_PARITY: usize = 36;
+const MINUTE_PARITY: usize = 37;
+/// Seconds of the announcement of a leap second
+const LEAP_SECOND_ANNOUNCEMENT: usize = 53;
+
+/// JJY time signal
+pub struct Jjy;
+
+impl TimeSignal for Jjy {
+    /// The carrier is on at the beginning of a second,
+    /// off at the end of the previous one.
+    const PHASE_PATTERN: PhasePattern = PhasePattern::new((900, 1000), (0, 100));
+
+    fn classify(polled_values: &PolledValues) -> Result<Dcf77SignalVariant, WorkflowError> {
+        match pulse_width(polled_values)? {
+            800 => Ok(Dcf77SignalVariant::Low),
+            500 => Ok(Dcf77SignalVariant::High),
+            _ => Ok(Dcf77SignalVariant::Marker),
+        }
+    }
+
+    fn follow(
+        previous: Option<Dcf77SignalVariant>,
+        symbol: Dcf77SignalVariant,
+    ) -> Dcf77SignalVariant {
+        follow_markers(previous, symbol)
+    }
+
+    fn decode(minute: &Minute) -> Result<Dcf77Frame, ProtocolError> {
+        let bits = marked_bits(minute)?;
+
+        // Check parity
+        if !check_even_parity(MINUTE.bits(&bits), bits[MINUTE_PARITY]) {
+            return Err(ProtocolError::MinuteChecksum);
+        }
+        if !check_even_parity(HOUR.bits(&bits), bits[HOUR_PARITY]) {
+            return Err(ProtocolError::HourChecksum);
+        }
+
+        let minute = MINUTE.value(&bits) as u8;
+        if minute > 59 {
+            return Err(ProtocolError::MinuteValue);
+        }
+
+        let hour = HOUR.value(&bits) as u8;
+        if hour > 23 {
+            return Err(ProtocolError::HourValue);
+        }
+
+        let year = YEAR.value(&bits) as u8;
+        if year > 99 {
+            return Err(ProtocolError::YearValue);
+        }
+
+        let date =
+            date_from_day_of_year(year, DAY_OF_YEAR.value(&bits)).ok_or(ProtocolError::DayValue)?;
+        let weekday = match DAY_OF_WEEK.value(&bits) as u8 {
+            0 => DayOfWeek::Sunday,
+            weekday @ 1..=6 => {
+                DayOfWeek::try_from(weekday).map_err(|_| ProtocolError::WeekdayValue)?
+            }
+            _ => return Err(ProtocolError::WeekdayValue),
+        };
+        if weekday != date.weekday {
+            return Err(ProtocolError::WeekdayMismatch);
+        }
+
+        // The frame is complete at the end of the marker beginning the next minute.
+        let datetime = Datetime {
+            date,
+            time: Time {
+                hour,
+                minute,
+                second: Some(1),
+            },
+        }
+        .add_minutes(1);
+
+        Ok(Dcf77Frame {
+            datetime,
+            utc_offset: UtcOffset::JST,
+            flags: Dcf77Flags {
+                call_bit: false,
+                summer_time_announcement: false,
+                leap_second_announcement: bits[LEAP_SECOND_ANNOUNCEMENT],
+            },
+        })
+    }
+
+    /// The bits of the minute in Japan Standard Time, the year and the day
+    /// of the week being transmitted at the minutes 15 and 45 as well.
+    #[cfg(feature = "generator")]
+    fn transmitted_during(
+        datetime: &Datetime,
+        utc_offset: UtcOffset,
+        rule: &SummerTimeRule,
+    ) -> [Dcf77SignalVariant; 60] {
+        let utc = datetime.to_utc(utc_offset);
+        let local = utc.to_local(rule.utc_offset(&utc));
+        let mut bits = [false; 60];
+        MINUTE.set(&mut bits, local.time.minute as u16);
+        HOUR.set(&mut bits, local.time.hour as u16);
+        DAY_OF_YEAR.set(&mut bits, day_of_year(&local.date));
+        bits[HOUR_PARITY] = even_parity(HOUR.bits(&bits));
+        bits[MINUTE_PARITY] = even_parity(MINUTE.bits(&bits));
+        YEAR.set(&mut bits, local.date.year as u16);
+        DAY_OF_WEEK.set(&mut bits, local.date.weekday as u16 % 7);
+        marked_symbols(&bits)
+    }
+
+    /// The polled value is high while the carrier is on.
+    #[cfg(feature = "generator")]
+    fn high_levels(symbol: Dcf77SignalVariant) -> &'static [(u16, u16)] {
+        match symbol {
+            Dcf77SignalVariant::Low => &[(0, 800)],
+            Dcf77SignalVariant::High => &[(0, 500)],
+            Dcf77SignalVariant::Marker => &[(0, 200)],
+            _ => &[],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-02-29 12:34 JST, Thursday, day 60 of a leap year
+    const FEBRUARY_29: &str = "01100100M 000100010M 000000110M 000000010M 000100100M 100000000M";
+
+    /// Decode the reference frame, with some seconds replaced.
+    fn decode_corrupted(
+        symbols: &[(usize, Dcf77SignalVariant)],
+    ) -> Result<Dcf77Frame, ProtocolError> {
+        let mut minute = Minute::from_symbols(FEBRUARY_29);
+        for &(second, symbol) in symbols {
+            minute.0[second - 1] = symbol;
+        }
+        Jjy::decode(&minute)
+    }
+
+    #[test]
+    fn reference_frame_is_decoded() {
+        let Ok(frame) = decode_corrupted(&[]) else {
+            panic!("frame not decoded");
+        };
+        // The frame is complete at the beginning of the next minute.
+        let mut expected = Datetime::at_minute(24, 2, 29, 12, 35).unwrap();
+        expected.time.second = Some(1);
+        assert!(frame.datetime == expected);
+        assert!(frame.datetime.date.weekday == DayOfWeek::Thursday);
+        assert_eq!(frame.utc_offset.0, 9);
+        assert!(!frame.flags.leap_second_announcement);
+    }
+
+    #[test]
+    fn corrupted_frames_are_rejected() {
+        use Dcf77SignalVariant::{High, Low};
+
+        assert!(matches!(
+            decode_corrupted(&[(29, Low)]),
+            Err(ProtocolError::Markers)
+        ));
+        // A single bit flipped, in the minute or in the hour
+        assert!(matches!(
+            decode_corrupted(&[(8, High)]),
+            Err(ProtocolError::MinuteChecksum)
+        ));
+        assert!(matches!(
+            decode_corrupted(&[(18, High)]),
+            Err(ProtocolError::HourChecksum)
+        ));
+        // Two bits flipped, out of range: minute 82, day of the week 7
+        assert!(matches!(
+            decode_corrupted(&[(1, High), (5, High)]),
+            Err(ProtocolError::MinuteValue)
+        ));
+        assert!(matches!(
+            decode_corrupted(&[(51, High), (52, High)]),
+            Err(ProtocolError::WeekdayValue)
+        ));
+        // Tuesday instead of Thursday
+        assert!(matches!(
+            decode_corrupted(&[(50, Low), (51, High)]),
+            Err(ProtocolError::WeekdayMismatch)
+        ));
+    }
+}
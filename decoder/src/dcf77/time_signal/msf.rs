@@ -0,0 +1,335 @@
+//! MSF, 60 kHz, Anthorn (United Kingdom): the carrier is off during
+//! the first 100 ms of each second, followed by two bits, A and B,
+//! of 100 ms each; it is off during 500 ms at the beginning of the minute.
+//! The bits A hold the UK civil time of the minute which follows
+//! (in packed BCD, MSB first), the bits B their odd parities and
+//! the state of the summer time.
+use super::{Field, Minute, TimeSignal};
+#[cfg(feature = "generator")]
+use crate::{dcf77::protocol::even_parity, SummerTimeRule};
+use crate::{
+    dcf77::{
+        polled_values::{samples_mask, PhasePattern, PolledValues, POLLED_SAMPLES_BYTES_PER_S},
+        protocol::{check_calendar, check_even_parity, Dcf77Flags, Dcf77Frame, ProtocolError},
+        Dcf77SignalVariant, WorkflowError,
+    },
+    Date, Datetime, DayOfWeek, Time, UtcOffset,
+};
+
+/// Samples of the beginning of the second, the carrier being always off
+const SECOND_MARKER: [u8; POLLED_SAMPLES_BYTES_PER_S] = samples_mask((0, 100));
+/// Samples of the bit A
+const BIT_A: [u8; POLLED_SAMPLES_BYTES_PER_S] = samples_mask((100, 200));
+/// Samples of the bit B
+const BIT_B: [u8; POLLED_SAMPLES_BYTES_PER_S] = samples_mask((200, 300));
+/// Samples following the bits, off only at the beginning of the minute
+const MINUTE_MARKER: [u8; POLLED_SAMPLES_BYTES_PER_S] = samples_mask((300, 500));
+
+/// Fields of the bits A
+const YEAR: Field = Field {
+    second: 17,
+    weights: &[80, 40, 20, 10, 8, 4, 2, 1],
+};
+const MONTH: Field = Field {
+    second: 25,
+    weights: &[10, 8, 4, 2, 1],
+};
+const DAY_OF_MONTH: Field = Field {
+    second: 30,
+    weights: &[20, 10, 8, 4, 2, 1],
+};
+const DAY_OF_WEEK: Field = Field {
+    second: 36,
+    weights: &[4, 2, 1],
+};
+const HOUR: Field = Field {
+    second: 39,
+    weights: &[20, 10, 8, 4, 2, 1],
+};
+const MINUTE: Field = Field {
+    second: 45,
+    weights: &[40, 20, 10, 8, 4, 2, 1],
+};
+/// First second of the fixed bits A, 01111110
+const FIXED_BITS: usize = 52;
+/// Seconds of the bits B
+const SUMMER_TIME_ANNOUNCEMENT: usize = 53;
+const YEAR_PARITY: usize = 54;
+const DATE_PARITY: usize = 55;
+const DAY_OF_WEEK_PARITY: usize = 56;
+const TIME_PARITY: usize = 57;
+const SUMMER_TIME: usize = 58;
+
+/// MSF time signal
+pub struct Msf;
+
+impl TimeSignal for Msf {
+    /// The carrier is off during the first 100 ms of a second,
+    /// and on at the end of the previous one.
+    const PHASE_PATTERN: PhasePattern = PhasePattern::new((900, 1000), (0, 100));
+
+    fn classify(polled_values: &PolledValues) -> Result<Dcf77SignalVariant, WorkflowError> {
+        // Protocol:
+        // - 100 ms = 4 polled bits at logical true => bit set
+        // - 200 ms = 8 polled bits at logical true after the bits => minute marker
+        let bit = |count| match count {
+            0..=1 => Ok(false),
+            3..=4 => Ok(true),
+            _ => Err(WorkflowError::BadBit(count)),
+        };
+        // A second without any pulse (missed by the receiver) is not a pair of bits.
+        let count = polled_values.count_high(&SECOND_MARKER);
+        if count < 3 {
+            return Err(WorkflowError::BadBit(count));
+        }
+        let a = bit(polled_values.count_high(&BIT_A))?;
+        let b = bit(polled_values.count_high(&BIT_B))?;
+        match polled_values.count_high(&MINUTE_MARKER) {
+            0..=2 => Ok(Dcf77SignalVariant::Pair(a, b)),
+            6..=8 if a && b => Ok(Dcf77SignalVariant::MinuteEnd),
+            count => Err(WorkflowError::BadBit(count)),
+        }
+    }
+
+    fn decode(minute: &Minute) -> Result<Dcf77Frame, ProtocolError> {
+        // Bits indexed by their second, from 1 (after the minute marker) to 59
+        let mut a = [false; 60];
+        let mut b = [false; 60];
+        for (second, symbol) in minute.0.iter().enumerate() {
+            match symbol {
+                Dcf77SignalVariant::Pair(bit_a, bit_b) => {
+                    a[second + 1] = *bit_a;
+                    b[second + 1] = *bit_b;
+                }
+                _ => return Err(ProtocolError::Markers),
+            }
+        }
+
+        // Check fixed values
+        if a[FIXED_BITS..] != [false, true, true, true, true, true, true, false] {
+            return Err(ProtocolError::Markers);
+        }
+
+        // Check the parities, which are odd
+        if check_even_parity(YEAR.bits(&a), b[YEAR_PARITY])
+            || check_even_parity(&a[MONTH.second..DAY_OF_WEEK.second], b[DATE_PARITY])
+            || check_even_parity(DAY_OF_WEEK.bits(&a), b[DAY_OF_WEEK_PARITY])
+        {
+            return Err(ProtocolError::DateChecksum);
+        }
+        if check_even_parity(&a[HOUR.second..FIXED_BITS], b[TIME_PARITY]) {
+            return Err(ProtocolError::HourChecksum);
+        }
+
+        let weekday = match DAY_OF_WEEK.value(&a) as u8 {
+            0 => DayOfWeek::Sunday,
+            weekday @ 1..=6 => {
+                DayOfWeek::try_from(weekday).map_err(|_| ProtocolError::WeekdayValue)?
+            }
+            _ => return Err(ProtocolError::WeekdayValue),
+        };
+
+        let minute = MINUTE.value(&a) as u8;
+        if minute > 59 {
+            return Err(ProtocolError::MinuteValue);
+        }
+
+        let hour = HOUR.value(&a) as u8;
+        if hour > 23 {
+            return Err(ProtocolError::HourValue);
+        }
+
+        let day = DAY_OF_MONTH.value(&a) as u8;
+        if day > 31 {
+            return Err(ProtocolError::DayValue);
+        }
+
+        let month = MONTH.value(&a) as u8;
+        if month > 12 {
+            return Err(ProtocolError::MonthValue);
+        }
+
+        let year = YEAR.value(&a) as u8;
+        if year > 99 {
+            return Err(ProtocolError::YearValue);
+        }
+
+        let date = Date {
+            year,
+            month,
+            day,
+            weekday,
+        };
+        check_calendar(&date)?;
+
+        Ok(Dcf77Frame {
+            datetime: Datetime {
+                date,
+                // The minute marker ends one second after the beginning of the minute.
+                time: Time {
+                    hour,
+                    minute,
+                    second: Some(1),
+                },
+            },
+            utc_offset: if b[SUMMER_TIME] {
+                UtcOffset::BST
+            } else {
+                UtcOffset::GMT
+            },
+            flags: Dcf77Flags {
+                call_bit: false,
+                summer_time_announcement: b[SUMMER_TIME_ANNOUNCEMENT],
+                leap_second_announcement: false,
+            },
+        })
+    }
+
+    /// The bits A of the following minute, the carrier being off
+    /// during the first 500 ms of the minute.
+    #[cfg(feature = "generator")]
+    fn transmitted_during(
+        datetime: &Datetime,
+        utc_offset: UtcOffset,
+        rule: &SummerTimeRule,
+    ) -> [Dcf77SignalVariant; 60] {
+        let frame = Dcf77Frame::transmitted_during(datetime, utc_offset, rule);
+        let (date, time) = (&frame.datetime.date, &frame.datetime.time);
+        let mut a = [false; 60];
+        let mut b = [false; 60];
+        YEAR.set(&mut a, date.year as u16);
+        MONTH.set(&mut a, date.month as u16);
+        DAY_OF_MONTH.set(&mut a, date.day as u16);
+        DAY_OF_WEEK.set(&mut a, date.weekday as u16 % 7);
+        HOUR.set(&mut a, time.hour as u16);
+        MINUTE.set(&mut a, time.minute as u16);
+        a[FIXED_BITS + 1..FIXED_BITS + 7].fill(true);
+
+        b[SUMMER_TIME_ANNOUNCEMENT] = frame.flags.summer_time_announcement;
+        b[YEAR_PARITY] = !even_parity(YEAR.bits(&a));
+        b[DATE_PARITY] = !even_parity(&a[MONTH.second..DAY_OF_WEEK.second]);
+        b[DAY_OF_WEEK_PARITY] = !even_parity(DAY_OF_WEEK.bits(&a));
+        b[TIME_PARITY] = !even_parity(&a[HOUR.second..FIXED_BITS]);
+        b[SUMMER_TIME] = frame.utc_offset == UtcOffset::BST;
+
+        let mut symbols = [Dcf77SignalVariant::MinuteEnd; 60];
+        for (second, symbol) in symbols.iter_mut().enumerate().skip(1) {
+            *symbol = Dcf77SignalVariant::Pair(a[second], b[second]);
+        }
+        symbols
+    }
+
+    /// The polled value is high while the carrier is off.
+    #[cfg(feature = "generator")]
+    fn high_levels(symbol: Dcf77SignalVariant) -> &'static [(u16, u16)] {
+        match symbol {
+            Dcf77SignalVariant::MinuteEnd => &[(0, 500)],
+            Dcf77SignalVariant::Pair(false, false) => &[(0, 100)],
+            Dcf77SignalVariant::Pair(true, false) => &[(0, 200)],
+            Dcf77SignalVariant::Pair(false, true) => &[(0, 100), (200, 300)],
+            Dcf77SignalVariant::Pair(true, true) => &[(0, 300)],
+            _ => &[],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bits A and B of 2023-12-25 07:30 GMT, Monday
+    const CHRISTMAS: (&str, &str) = (
+        "000000000 0000000001 0001110010 1001010010 0011101100 0001111110",
+        "000000000 0000000000 0000000000 0000000000 0000000000 0000000000",
+    );
+    /// Bits A and B of 2024-06-21 16:05 BST, Friday
+    const MIDSUMMER: (&str, &str) = (
+        "000000000 0000000001 0010000110 1000011010 1011000001 0101111110",
+        "000000000 0000000000 0000000000 0000000000 0000000000 0000111010",
+    );
+
+    /// Minute of the bits A and B of the seconds 1 to 59,
+    /// written as `0` and `1`, whitespace ignored.
+    fn minute((a, b): (&'static str, &'static str)) -> Minute {
+        let bits = |bits: &'static str| {
+            bits.chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|c| c == '1')
+        };
+        let mut minute = Minute([Dcf77SignalVariant::Unknown; 59]);
+        for (second, (a, b)) in minute.0.iter_mut().zip(bits(a).zip(bits(b))) {
+            *second = Dcf77SignalVariant::Pair(a, b);
+        }
+        minute
+    }
+
+    /// Decode the reference frame of Christmas, with some bits A flipped.
+    fn decode_corrupted(seconds: &[usize]) -> Result<Dcf77Frame, ProtocolError> {
+        let mut minute = minute(CHRISTMAS);
+        for &second in seconds {
+            if let Dcf77SignalVariant::Pair(a, b) = minute.0[second - 1] {
+                minute.0[second - 1] = Dcf77SignalVariant::Pair(!a, b);
+            }
+        }
+        Msf::decode(&minute)
+    }
+
+    #[test]
+    fn reference_frames_are_decoded() {
+        for (bits, (year, month, day, hour, minute_of_hour), utc_offset) in [
+            (CHRISTMAS, (23, 12, 25, 7, 30), UtcOffset::GMT),
+            (MIDSUMMER, (24, 6, 21, 16, 5), UtcOffset::BST),
+        ] {
+            let Ok(frame) = Msf::decode(&minute(bits)) else {
+                panic!("frame not decoded");
+            };
+            // The minute marker ends one second after the beginning of the minute.
+            let mut expected = Datetime::at_minute(year, month, day, hour, minute_of_hour).unwrap();
+            expected.time.second = Some(1);
+            assert!(frame.datetime == expected);
+            assert_eq!(frame.utc_offset.0, utc_offset.0);
+            assert!(!frame.flags.summer_time_announcement);
+        }
+    }
+
+    #[test]
+    fn corrupted_frames_are_rejected() {
+        // Fixed bits
+        assert!(matches!(
+            decode_corrupted(&[52]),
+            Err(ProtocolError::Markers)
+        ));
+        let mut symbols = minute(CHRISTMAS);
+        symbols.0[0] = Dcf77SignalVariant::MinuteEnd;
+        assert!(matches!(Msf::decode(&symbols), Err(ProtocolError::Markers)));
+        // A single bit flipped, in the year, the month, the day of the week or the time
+        for second in [17, 25, 36] {
+            assert!(matches!(
+                decode_corrupted(&[second]),
+                Err(ProtocolError::DateChecksum)
+            ));
+        }
+        assert!(matches!(
+            decode_corrupted(&[45]),
+            Err(ProtocolError::HourChecksum)
+        ));
+        // Two bits flipped, out of range: minute 78, month 24, day 37
+        assert!(matches!(
+            decode_corrupted(&[45, 48]),
+            Err(ProtocolError::MinuteValue)
+        ));
+        assert!(matches!(
+            decode_corrupted(&[26, 27]),
+            Err(ProtocolError::MonthValue)
+        ));
+        assert!(matches!(
+            decode_corrupted(&[31, 34]),
+            Err(ProtocolError::DayValue)
+        ));
+        // Tuesday instead of Monday
+        assert!(matches!(
+            decode_corrupted(&[37, 38]),
+            Err(ProtocolError::WeekdayMismatch)
+        ));
+    }
+}
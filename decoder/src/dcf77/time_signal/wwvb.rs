@@ -0,0 +1,244 @@
+//! WWVB, 60 kHz, Fort Collins (United States): the carrier is reduced
+//! at the beginning of each second during 200 ms (bit 0), 500 ms (bit 1)
+//! or 800 ms (position marker, every ten seconds and at the beginning
+//! of the minute).
+//! The bits hold the UTC time of the current minute (in BCD, MSB first),
+//! along with the state of the summer time in the United States:
+//! the local time is derived from the offsets of the rule of summer time.
+use super::{
+    date_from_day_of_year, follow_markers, marked_bits, pulse_width, Field, Minute, TimeSignal,
+};
+#[cfg(feature = "generator")]
+use super::{day_of_year, marked_symbols};
+use crate::{
+    dcf77::{
+        polled_values::{PhasePattern, PolledValues},
+        protocol::{Dcf77Flags, Dcf77Frame, ProtocolError},
+        Dcf77SignalVariant, WorkflowError, SUMMER_TIME_RULE,
+    },
+    Datetime, Time,
+};
+#[cfg(feature = "generator")]
+use crate::{SummerTimeRule, UtcOffset};
+
+/// Fields of the bits
+const MINUTE: Field = Field {
+    second: 1,
+    weights: &[40, 20, 10, 0, 8, 4, 2, 1],
+};
+const HOUR: Field = Field {
+    second: 12,
+    weights: &[20, 10, 0, 8, 4, 2, 1],
+};
+const DAY_OF_YEAR: Field = Field {
+    second: 22,
+    weights: &[200, 100, 0, 80, 40, 20, 10, 0, 8, 4, 2, 1],
+};
+const YEAR: Field = Field {
+    second: 45,
+    weights: &[80, 40, 20, 10, 0, 8, 4, 2, 1],
+};
+/// Seconds of the flags
+const LEAP_YEAR: usize = 55;
+const LEAP_SECOND_ANNOUNCEMENT: usize = 56;
+/// Summer time at the end of the day (24:00 UTC)
+const SUMMER_TIME_AT_DAY_END: usize = 57;
+/// Summer time at the beginning of the day (00:00 UTC)
+const SUMMER_TIME_AT_DAY_START: usize = 58;
+
+/// WWVB time signal
+pub struct Wwvb;
+
+impl TimeSignal for Wwvb {
+    /// The carrier is reduced at the beginning of a second,
+    /// never at the end of the previous one.
+    const PHASE_PATTERN: PhasePattern = PhasePattern::new((900, 1000), (0, 100));
+
+    fn classify(polled_values: &PolledValues) -> Result<Dcf77SignalVariant, WorkflowError> {
+        match pulse_width(polled_values)? {
+            200 => Ok(Dcf77SignalVariant::Low),
+            500 => Ok(Dcf77SignalVariant::High),
+            _ => Ok(Dcf77SignalVariant::Marker),
+        }
+    }
+
+    fn follow(
+        previous: Option<Dcf77SignalVariant>,
+        symbol: Dcf77SignalVariant,
+    ) -> Dcf77SignalVariant {
+        follow_markers(previous, symbol)
+    }
+
+    fn decode(minute: &Minute) -> Result<Dcf77Frame, ProtocolError> {
+        let bits = marked_bits(minute)?;
+
+        let minute = MINUTE.value(&bits) as u8;
+        if minute > 59 {
+            return Err(ProtocolError::MinuteValue);
+        }
+
+        let hour = HOUR.value(&bits) as u8;
+        if hour > 23 {
+            return Err(ProtocolError::HourValue);
+        }
+
+        let year = YEAR.value(&bits) as u8;
+        if year > 99 {
+            return Err(ProtocolError::YearValue);
+        }
+        // Without any parity, check at least the consistency of the year
+        if bits[LEAP_YEAR] != (year % 4 == 0) {
+            return Err(ProtocolError::DateChecksum);
+        }
+
+        let date =
+            date_from_day_of_year(year, DAY_OF_YEAR.value(&bits)).ok_or(ProtocolError::DayValue)?;
+
+        // The frame is complete at the end of the marker beginning the next minute.
+        let utc = Datetime {
+            date,
+            time: Time {
+                hour,
+                minute,
+                second: Some(1),
+            },
+        }
+        .add_minutes(1);
+        let utc_offset = match (bits[SUMMER_TIME_AT_DAY_START], bits[SUMMER_TIME_AT_DAY_END]) {
+            (false, false) => SUMMER_TIME_RULE.standard,
+            (true, true) => SUMMER_TIME_RULE.summer,
+            // The summer time begins or ends this day.
+            _ => SUMMER_TIME_RULE.utc_offset(&utc),
+        };
+
+        Ok(Dcf77Frame {
+            datetime: utc.to_local(utc_offset),
+            utc_offset,
+            flags: Dcf77Flags {
+                call_bit: false,
+                summer_time_announcement: false,
+                leap_second_announcement: bits[LEAP_SECOND_ANNOUNCEMENT],
+            },
+        })
+    }
+
+    /// The bits of the minute in UTC, with the state of the summer time
+    /// at the beginning and at the end of its day.
+    #[cfg(feature = "generator")]
+    fn transmitted_during(
+        datetime: &Datetime,
+        utc_offset: UtcOffset,
+        rule: &SummerTimeRule,
+    ) -> [Dcf77SignalVariant; 60] {
+        let utc = datetime.to_utc(utc_offset);
+        let mut bits = [false; 60];
+        MINUTE.set(&mut bits, utc.time.minute as u16);
+        HOUR.set(&mut bits, utc.time.hour as u16);
+        DAY_OF_YEAR.set(&mut bits, day_of_year(&utc.date));
+        YEAR.set(&mut bits, utc.date.year as u16);
+        bits[LEAP_YEAR] = utc.date.year % 4 == 0;
+
+        let day_start = Datetime {
+            time: Time {
+                hour: 0,
+                minute: 0,
+                second: None,
+            },
+            ..utc
+        };
+        let day_end = day_start.add_minutes(24 * 60);
+        bits[SUMMER_TIME_AT_DAY_START] = rule.utc_offset(&day_start) == rule.summer;
+        bits[SUMMER_TIME_AT_DAY_END] = rule.utc_offset(&day_end) == rule.summer;
+        marked_symbols(&bits)
+    }
+
+    /// The polled value is high while the carrier is reduced.
+    #[cfg(feature = "generator")]
+    fn high_levels(symbol: Dcf77SignalVariant) -> &'static [(u16, u16)] {
+        match symbol {
+            Dcf77SignalVariant::Low => &[(0, 200)],
+            Dcf77SignalVariant::High => &[(0, 500)],
+            Dcf77SignalVariant::Marker => &[(0, 800)],
+            _ => &[],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2023-07-04 18:42 UTC, day 185, summer time all day long
+    const JULY_4: &str = "10000010M 000101000M 000101000M 010100000M 000000010M 001100011M";
+    /// 2024-03-10 06:30 UTC, day 70 of a leap year, beginning of the summer time
+    /// at 07:00 UTC (02:00 local time)
+    const MARCH_10: &str = "01100000M 000000110M 000000111M 000000000M 000000010M 010001010M";
+
+    /// Local datetime at the end of the marker beginning a minute
+    fn local(year: u8, month: u8, day: u8, hour: u8, minute: u8) -> Datetime {
+        let mut datetime = Datetime::at_minute(year, month, day, hour, minute).unwrap();
+        datetime.time.second = Some(1);
+        datetime
+    }
+
+    #[test]
+    fn reference_frames_are_decoded() {
+        let Ok(frame) = Wwvb::decode(&Minute::from_symbols(JULY_4)) else {
+            panic!("frame not decoded");
+        };
+        assert!(frame.datetime == local(23, 7, 4, 14, 43));
+        assert_eq!(frame.utc_offset.0, -4);
+        assert!(!frame.flags.leap_second_announcement);
+
+        // The offset changes during the day: it is given by the rule.
+        let mut minute = Minute::from_symbols(MARCH_10);
+        let Ok(frame) = Wwvb::decode(&minute) else {
+            panic!("frame not decoded");
+        };
+        assert!(frame.datetime == local(24, 3, 10, 1, 31));
+        assert_eq!(frame.utc_offset.0, -5);
+        // One hour later (hour 07, bit of weight 1 set)
+        minute.0[17] = Dcf77SignalVariant::High;
+        let Ok(frame) = Wwvb::decode(&minute) else {
+            panic!("frame not decoded");
+        };
+        assert!(frame.datetime == local(24, 3, 10, 3, 31));
+        assert_eq!(frame.utc_offset.0, -4);
+    }
+
+    #[test]
+    fn corrupted_frames_are_rejected() {
+        let corrupted = |second: usize, symbol| {
+            let mut minute = Minute::from_symbols(JULY_4);
+            minute.0[second - 1] = symbol;
+            Wwvb::decode(&minute)
+        };
+        // Missing marker, and marker instead of a bit
+        assert!(matches!(
+            corrupted(19, Dcf77SignalVariant::Low),
+            Err(ProtocolError::Markers)
+        ));
+        assert!(matches!(
+            corrupted(20, Dcf77SignalVariant::Marker),
+            Err(ProtocolError::Markers)
+        ));
+        // Leap year indicator inconsistent with the year
+        assert!(matches!(
+            corrupted(LEAP_YEAR, Dcf77SignalVariant::High),
+            Err(ProtocolError::DateChecksum)
+        ));
+        // Out of range: minute 62, hour 38, day 385
+        assert!(matches!(
+            corrupted(2, Dcf77SignalVariant::High),
+            Err(ProtocolError::MinuteValue)
+        ));
+        assert!(matches!(
+            corrupted(12, Dcf77SignalVariant::High),
+            Err(ProtocolError::HourValue)
+        ));
+        assert!(matches!(
+            corrupted(22, Dcf77SignalVariant::High),
+            Err(ProtocolError::DayValue)
+        ));
+    }
+}
@@ -0,0 +1,12 @@
+//! Hardware-independent part of the alarm clock: the datetimes and the decoder
+//! of the time signals, fed with the polled values of the receiver.
+//! It builds on a host as well, to replay captures in tests.
+#![no_std]
+
+pub use datetime::{
+    ClockDatetime, Date, Datetime, DayOfWeek, PhaseOfDay, SummerTimeRule, Time, UtcOffset,
+};
+
+pub mod datetime;
+pub mod dcf77;
+pub mod timer;
@@ -0,0 +1,70 @@
+//! Timer of the polled values, in milliseconds, wrapping periodically.
+/// Downsampling factor for the polling of the DCF77 input
+pub const POLLED_SAMPLES_PERIOD_MS: u16 = 25;
+/// The downsampling factor is based on the modulo of the timer.
+/// The rolling of the timer must occur at the biggest multiple of the POLLED_SAMPLES_PERIOD_MS
+/// without overflowing the capacity of the integer type.
+const TIMER_MAX: u16 = (u16::MAX / POLLED_SAMPLES_PERIOD_MS) * POLLED_SAMPLES_PERIOD_MS;
+
+/// Timer structure, new-type pattern
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timer(pub u16);
+
+impl ufmt::uDisplay for Timer {
+    /// Formatter for the serial output.
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        self.0.fmt(f)
+    }
+}
+
+impl Timer {
+    /// Increment the timer by a number of milliseconds, wrapping at TIMER_MAX.
+    pub fn increment(&mut self, millis: u16) {
+        self.0 += millis;
+        if self.0 > TIMER_MAX {
+            self.0 = 1;
+        }
+    }
+}
+
+impl core::ops::Sub for Timer {
+    type Output = Timer;
+
+    /// Difference of timer, taking into account wrapping.
+    /// The result is meaningful only if the time between both arguments
+    /// are less than TIMER_MAX apart.
+    fn sub(self, rhs: Self) -> Self::Output {
+        Timer(if self.0 >= rhs.0 {
+            self.0 - rhs.0
+        } else {
+            let rhs = TIMER_MAX - rhs.0;
+            self.0 + rhs
+        })
+    }
+}
+
+impl core::ops::Add for Timer {
+    type Output = Timer;
+
+    /// Sum of timers, taking into account wrapping.
+    fn add(self, rhs: Self) -> Self::Output {
+        let sum = self.0 as u32 + rhs.0 as u32;
+        Timer(if sum > TIMER_MAX as u32 {
+            (sum - TIMER_MAX as u32) as u16
+        } else {
+            sum as u16
+        })
+    }
+}
+
+impl core::ops::Rem<u16> for Timer {
+    type Output = u16;
+
+    /// Modulo operation on the timer.
+    fn rem(self, rhs: u16) -> Self::Output {
+        self.0 % rhs
+    }
+}
@@ -0,0 +1,200 @@
+//! Decoding of synthetic signals (see `CaptureGenerator`) with random datetimes
+//! and noise: up to a specified level of each kind of noise, the exact datetime
+//! is published within a few minutes; above, the decoder may miss some minutes,
+//! but never publishes a wrong datetime.
+//! The time signal is the one selected by the cargo features.
+#![cfg(not(feature = "dcf77-int0"))]
+use alarm_clock_decoder::{
+    dcf77::{CaptureGenerator, Dcf77, Noise, SUMMER_TIME_RULE},
+    Datetime,
+};
+use proptest::prelude::*;
+
+/// Number of minutes of the clean and of the heavily noisy signals
+const MINUTES: u16 = 6;
+
+/// Minute of the signal at the beginning of which the first datetime is published,
+/// once the phase is locked and the first frame checked against the second one
+#[cfg(not(feature = "msf"))]
+const FIRST_MINUTE: i16 = 3;
+#[cfg(feature = "msf")]
+const FIRST_MINUTE: i16 = 2;
+
+/// Local datetime, in the offset of the rule of summer time,
+/// of a whole number of minutes after a datetime in UTC.
+fn local(utc: &Datetime, minutes: i16) -> Datetime {
+    let utc = utc.add_minutes(minutes);
+    utc.to_local(SUMMER_TIME_RULE.utc_offset(&utc))
+}
+
+/// Datetime in UTC: year (from 2000), month, day, hour and minute
+type Utc = (u8, u8, u8, u8, u8);
+
+/// Datetimes in UTC, from 2000 to 2099
+fn utc_datetimes() -> impl Strategy<Value = Utc> {
+    (0u8..100, 1u8..=12, 1u8..=31, 0u8..24, 0u8..60)
+        .prop_filter("day out of the month", |&utc| datetime(utc).is_some())
+}
+
+/// Datetimes in UTC shortly before a change of offset of the rule of summer time,
+/// if any in the months of its transitions (none in Japan).
+fn transition_datetimes() -> impl Strategy<Value = Utc> {
+    let months = vec![SUMMER_TIME_RULE.start.month, SUMMER_TIME_RULE.end.month];
+    (0u8..100, prop::sample::select(months), 50u8..60).prop_map(|(year, month, minute)| {
+        let offset = |utc: &Datetime| SUMMER_TIME_RULE.utc_offset(utc).0;
+        let first = datetime((year, month, 1, 0, minute)).unwrap();
+        let utc = core::iter::successors(Some(first), |utc| Some(utc.add_minutes(60)))
+            .take_while(|utc| utc.date.month == month)
+            .find(|utc| offset(utc) != offset(&utc.add_minutes(10)))
+            .unwrap_or(first);
+        (year, month, utc.date.day, utc.time.hour, minute)
+    })
+}
+
+/// Datetimes in UTC, a fair share of them around a change of offset
+fn any_datetimes() -> impl Strategy<Value = Utc> {
+    prop_oneof![3 => utc_datetimes(), 1 => transition_datetimes()]
+}
+
+/// Datetime of its fields, if they are within their ranges.
+fn datetime((year, month, day, hour, minute): Utc) -> Option<Datetime> {
+    Datetime::at_minute(year, month, day, hour, minute)
+}
+
+/// Decode a signal of a number of minutes, and return the published datetimes
+/// with the minute of the signal at the beginning of which they were published.
+fn decode(utc: &Datetime, minutes: u16, noise: Noise, seed: u32) -> Vec<(i16, Datetime)> {
+    let start = local(utc, 0);
+    let generator = CaptureGenerator::new(
+        &start,
+        SUMMER_TIME_RULE.utc_offset(utc),
+        SUMMER_TIME_RULE,
+        minutes,
+        noise,
+        seed,
+    );
+    let mut dcf77 = Dcf77::default();
+    let mut frames = Vec::new();
+    for (index, sample) in generator.enumerate() {
+        if let Ok((_, Some(frame))) = dcf77.run(Some(sample), None) {
+            // A frame is published at the beginning of its minute, the capture
+            // beginning one second before the first minute.
+            let minute = ((index as i32 * 25 - 1000 + 30_000) / 60_000) as i16;
+            frames.push((minute, frame.datetime));
+        }
+        dcf77.take_timer_correction();
+    }
+    frames
+}
+
+/// Check the published datetimes: each one is the beginning
+/// of the minute at which it was published.
+fn check(utc: &Datetime, frames: &[(i16, Datetime)]) -> Result<(), TestCaseError> {
+    for &(minute, datetime) in frames {
+        let expected = local(utc, minute);
+        prop_assert!(
+            (datetime.date, datetime.time.hour, datetime.time.minute)
+                == (expected.date, expected.time.hour, expected.time.minute),
+            "{:02}-{:02}-{:02} {:02}:{:02} published instead of {:02}-{:02}-{:02} {:02}:{:02}",
+            datetime.date.year,
+            datetime.date.month,
+            datetime.date.day,
+            datetime.time.hour,
+            datetime.time.minute,
+            expected.date.year,
+            expected.date.month,
+            expected.date.day,
+            expected.time.hour,
+            expected.time.minute
+        );
+    }
+    Ok(())
+}
+
+/// Check that a noisy signal is decoded: the datetimes are published
+/// from a minute at the latest, each one being right.
+fn check_recovered(
+    utc: &Datetime,
+    noise: Noise,
+    seed: u32,
+    minute: i16,
+) -> Result<(), TestCaseError> {
+    let frames = decode(utc, minute as u16 + 1, noise, seed);
+    check(utc, &frames)?;
+    let first = frames.first().map(|&(minute, _)| minute);
+    prop_assert!(
+        first.is_some_and(|first| first <= minute),
+        "first datetime published at the minute {:?}",
+        first
+    );
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn clean_signal_is_decoded(utc in any_datetimes(), drift_ppm in -200i16..=200) {
+        let utc = datetime(utc).unwrap();
+        let noise = Noise { drift_ppm, ..Noise::default() };
+        let frames = decode(&utc, MINUTES, noise, 0);
+        check(&utc, &frames)?;
+        // The first frame is checked against the second one; the frame
+        // of the last minute may be published with the last polled value.
+        let minutes: Vec<i16> = frames.iter().map(|&(minute, _)| minute).collect();
+        prop_assert!(
+            minutes == (FIRST_MINUTE..MINUTES as i16).collect::<Vec<_>>()
+                || minutes == (FIRST_MINUTE..=MINUTES as i16).collect::<Vec<_>>(),
+            "frames published at the minutes {:?}",
+            minutes
+        );
+    }
+
+    #[test]
+    fn jittery_signal_is_decoded(utc in any_datetimes(), jitter_ms in 0u16..=10, seed: u32) {
+        let utc = datetime(utc).unwrap();
+        let noise = Noise { jitter_ms, ..Noise::default() };
+        check_recovered(&utc, noise, seed, FIRST_MINUTE)?;
+    }
+
+    #[test]
+    fn signal_with_dropouts_is_decoded(utc in any_datetimes(), dropouts in 0u16..=2, seed: u32) {
+        let utc = datetime(utc).unwrap();
+        let noise = Noise { dropouts, ..Noise::default() };
+        // A missing pulse spoils its minute: at 2 per mille, about one minute
+        // in nine, and two consecutive minutes are needed.
+        check_recovered(&utc, noise, seed, 14)?;
+    }
+
+    #[test]
+    fn signal_with_spikes_is_decoded(utc in any_datetimes(), spikes in 0u16..=5, seed: u32) {
+        let utc = datetime(utc).unwrap();
+        let noise = Noise { spikes, ..Noise::default() };
+        // A flipped polled value may spoil its minute too, but more seldom.
+        check_recovered(&utc, noise, seed, 10)?;
+    }
+
+    #[test]
+    fn drifting_signal_is_decoded(utc in any_datetimes(), drift_ppm in -3000i16..=3000) {
+        let utc = datetime(utc).unwrap();
+        let noise = Noise { drift_ppm, ..Noise::default() };
+        check_recovered(&utc, noise, 0, FIRST_MINUTE)?;
+    }
+
+    #[test]
+    fn noisy_signal_is_never_wrong(
+        utc in any_datetimes(),
+        jitter_ms in 0u16..=40,
+        dropouts in 0u16..=50,
+        spikes in 0u16..=20,
+        drift_ppm in -500i16..=500,
+        seed: u32,
+    ) {
+        // A flipped polled value may corrupt the bits of any frame, and the noise
+        // may be too heavy for any to be received: only check that no wrong datetime
+        // is ever published.
+        let utc = datetime(utc).unwrap();
+        let noise = Noise { jitter_ms, dropouts, spikes, drift_ppm };
+        check(&utc, &decode(&utc, MINUTES, noise, seed))?;
+    }
+}
@@ -0,0 +1,140 @@
+//! Replay of capture files (see `tools/dcf77_capture.py`) through the decoder,
+//! checking the published datetimes and the errors met on the way.
+//! The captures of `captures/` are synthetic, generated by `examples/dcf77_generate.rs`
+//! with the arguments written on their first line. No capture recorded from a receiver
+//! is checked in yet: one recorded by `tools/dcf77_capture.py` is replayed the same way,
+//! with a test asserting the datetimes and the errors met on it.
+#![cfg(not(any(
+    feature = "msf",
+    feature = "wwvb",
+    feature = "jjy",
+    feature = "dcf77-int0"
+)))]
+use alarm_clock_decoder::dcf77::{CaptureSamples, Dcf77, Dcf77Frame};
+
+/// Local datetime of a frame: year, month, day, hour, minute and offset
+type Minute = (u8, u8, u8, u8, u8, i8);
+
+/// Local datetime of a frame, comparable in assertions.
+fn minute(frame: &Dcf77Frame) -> Minute {
+    let Dcf77Frame {
+        datetime,
+        utc_offset,
+        ..
+    } = frame;
+    (
+        datetime.date.year,
+        datetime.date.month,
+        datetime.date.day,
+        datetime.time.hour,
+        datetime.time.minute,
+        utc_offset.0,
+    )
+}
+
+/// Feed the decoder with all the samples of a capture,
+/// and return it with the frames it published.
+fn replay(capture: &str) -> (Dcf77, Vec<Minute>) {
+    let mut dcf77 = Dcf77::default();
+    let mut frames = Vec::new();
+    for sample in CaptureSamples::new(capture) {
+        if let Ok((_, Some(frame))) = dcf77.run(Some(sample), None) {
+            frames.push(minute(&frame));
+        }
+        // No timer to correct on a host
+        dcf77.take_timer_correction();
+    }
+    (dcf77, frames)
+}
+
+#[test]
+fn clean_signal_across_the_end_of_summer_time() {
+    let (dcf77, frames) = replay(include_str!("captures/dcf77_clean.txt"));
+    // The first frame has no previous one to be checked against,
+    // and the last one is not closed by a minute end.
+    assert_eq!(
+        frames,
+        [
+            (24, 10, 27, 2, 58, 2),
+            (24, 10, 27, 2, 59, 2),
+            (24, 10, 27, 2, 0, 1),
+            (24, 10, 27, 2, 1, 1),
+            (24, 10, 27, 2, 2, 1),
+        ]
+    );
+    let metrics = dcf77.metrics();
+    assert_eq!(metrics.workflow_errors, [0; 5]);
+    assert_eq!(metrics.protocol_errors, [0; 17]);
+    assert_eq!(metrics.frames_decoded, 6);
+    assert_eq!(dcf77.rejected_frames(), 1);
+}
+
+#[test]
+fn missed_samples_as_streamed_by_the_board() {
+    // Lines as streamed over the serial port, a second of samples being lost
+    // during the minute of 02:58.
+    let capture: String = include_str!("captures/dcf77_clean.txt")
+        .lines()
+        .filter(|line| !line.starts_with("34500 "))
+        .map(|line| format!("${}\n", line))
+        .collect();
+    let (dcf77, frames) = replay(&capture);
+    assert_eq!(
+        frames,
+        [
+            (24, 10, 27, 2, 59, 2),
+            (24, 10, 27, 2, 0, 1),
+            (24, 10, 27, 2, 1, 1),
+            (24, 10, 27, 2, 2, 1),
+        ]
+    );
+    let metrics = dcf77.metrics();
+    assert_eq!(metrics.workflow_errors, [1, 0, 0, 0, 0]);
+    assert_eq!(metrics.protocol_errors, [0; 17]);
+}
+
+#[test]
+fn malformed_lines_are_skipped() {
+    let capture = "# comment\n0 4 f\n100 4 g\n200 6 0f\nnot a line\n400 1 8\n";
+    let samples: Vec<(u16, bool)> = CaptureSamples::new(capture)
+        .map(|(timer, value)| (timer.0, value))
+        .collect();
+    assert_eq!(
+        samples,
+        [
+            (0, true),
+            (25, true),
+            (50, true),
+            (75, true),
+            (200, false),
+            (225, false),
+            (250, false),
+            (275, false),
+            (300, true),
+            (325, true),
+            (400, true),
+        ]
+    );
+}
+
+#[test]
+fn noisy_signal_across_the_beginning_of_summer_time() {
+    let (dcf77, frames) = replay(include_str!("captures/dcf77_noisy.txt"));
+    // The minutes with a missing pulse are lost, but no wrong datetime is published.
+    assert_eq!(
+        frames,
+        [
+            (24, 3, 31, 1, 59, 1),
+            (24, 3, 31, 3, 0, 2),
+            (24, 3, 31, 3, 1, 2),
+            (24, 3, 31, 3, 2, 2),
+            (24, 3, 31, 3, 3, 2),
+            (24, 3, 31, 3, 4, 2),
+            (24, 3, 31, 3, 5, 2),
+        ]
+    );
+    let metrics = dcf77.metrics();
+    assert_eq!(metrics.workflow_errors, [0; 5]);
+    assert_eq!(metrics.protocol_errors, [0; 17]);
+    assert_eq!(dcf77.rejected_frames(), 1);
+}
@@ -0,0 +1,91 @@
+//! Offsets given by the rules of summer time around their transitions,
+//! and on the first and last days of the months of the transitions.
+use alarm_clock_decoder::{
+    datetime::{
+        EU_SUMMER_TIME_RULE, JAPAN_TIME_RULE, UK_SUMMER_TIME_RULE, US_EASTERN_SUMMER_TIME_RULE,
+    },
+    Datetime, DayOfWeek, SummerTimeRule,
+};
+
+/// Datetime in UTC, from the year 2000.
+fn utc(year: u8, month: u8, day: u8, hour: u8, minute: u8) -> Datetime {
+    Datetime::at_minute(year, month, day, hour, minute).unwrap()
+}
+
+/// Offset to UTC, in hours, given by a rule at a datetime in UTC.
+fn offset(rule: &SummerTimeRule, utc: Datetime) -> i8 {
+    rule.utc_offset(&utc).0
+}
+
+#[test]
+fn eu_transitions_on_the_last_sunday_at_one_utc() {
+    // Years whose last Sunday of March and October fall on various days,
+    // from the 25th, the earliest, to the 31st, the latest.
+    for (year, march, october) in [(24, 31, 27), (26, 29, 25), (27, 28, 31), (29, 25, 28)] {
+        let rule = &EU_SUMMER_TIME_RULE;
+        assert!(utc(year, 3, march, 0, 0).date.weekday == DayOfWeek::Sunday);
+        assert!(utc(year, 10, october, 0, 0).date.weekday == DayOfWeek::Sunday);
+
+        assert_eq!(offset(rule, utc(year, 3, march, 0, 59)), 1);
+        assert_eq!(offset(rule, utc(year, 3, march, 1, 0)), 2);
+        assert_eq!(offset(rule, utc(year, 10, october, 0, 59)), 2);
+        assert_eq!(offset(rule, utc(year, 10, october, 1, 0)), 1);
+        // The Sunday before does not change the offset.
+        assert_eq!(offset(rule, utc(year, 3, march - 7, 1, 0)), 1);
+        assert_eq!(offset(rule, utc(year, 10, october - 7, 1, 0)), 2);
+    }
+}
+
+#[test]
+fn eu_first_and_last_days_of_the_months() {
+    for year in [24, 26, 27, 29] {
+        let rule = &EU_SUMMER_TIME_RULE;
+        assert_eq!(offset(rule, utc(year, 1, 1, 0, 0)), 1);
+        assert_eq!(offset(rule, utc(year, 3, 1, 0, 0)), 1);
+        assert_eq!(offset(rule, utc(year, 3, 31, 23, 59)), 2);
+        assert_eq!(offset(rule, utc(year, 4, 1, 0, 0)), 2);
+        assert_eq!(offset(rule, utc(year, 9, 30, 23, 59)), 2);
+        assert_eq!(offset(rule, utc(year, 10, 1, 0, 0)), 2);
+        assert_eq!(offset(rule, utc(year, 10, 31, 23, 59)), 1);
+        assert_eq!(offset(rule, utc(year, 11, 1, 0, 0)), 1);
+        assert_eq!(offset(rule, utc(year, 12, 31, 23, 59)), 1);
+    }
+}
+
+#[test]
+fn uk_transitions_at_the_same_instants_as_the_eu() {
+    assert_eq!(offset(&UK_SUMMER_TIME_RULE, utc(24, 3, 31, 0, 59)), 0);
+    assert_eq!(offset(&UK_SUMMER_TIME_RULE, utc(24, 3, 31, 1, 0)), 1);
+    assert_eq!(offset(&UK_SUMMER_TIME_RULE, utc(24, 10, 27, 0, 59)), 1);
+    assert_eq!(offset(&UK_SUMMER_TIME_RULE, utc(24, 10, 27, 1, 0)), 0);
+}
+
+#[test]
+fn us_eastern_transitions_at_two_local_time() {
+    // Second Sunday of March at 07:00 UTC, first Sunday of November at 06:00 UTC,
+    // the latter being the first day of the month in 2026.
+    for (year, march, november) in [(24, 10, 3), (26, 8, 1), (27, 14, 7)] {
+        let rule = &US_EASTERN_SUMMER_TIME_RULE;
+        assert_eq!(offset(rule, utc(year, 3, march, 6, 59)), -5);
+        assert_eq!(offset(rule, utc(year, 3, march, 7, 0)), -4);
+        assert_eq!(offset(rule, utc(year, 11, november, 5, 59)), -4);
+        assert_eq!(offset(rule, utc(year, 11, november, 6, 0)), -5);
+        assert_eq!(offset(rule, utc(year, 3, 1, 0, 0)), -5);
+        assert_eq!(offset(rule, utc(year, 3, 31, 23, 59)), -4);
+        assert_eq!(offset(rule, utc(year, 10, 31, 23, 59)), -4);
+        assert_eq!(offset(rule, utc(year, 11, 30, 23, 59)), -5);
+    }
+}
+
+#[test]
+fn japan_has_no_summer_time() {
+    for datetime in [
+        utc(24, 1, 1, 0, 0),
+        utc(24, 3, 31, 1, 0),
+        utc(24, 7, 1, 12, 0),
+        utc(24, 10, 27, 0, 59),
+        utc(24, 12, 31, 23, 59),
+    ] {
+        assert_eq!(offset(&JAPAN_TIME_RULE, datetime), 9);
+    }
+}
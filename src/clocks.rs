@@ -1,31 +1,60 @@
 //! Clocks, date and time management
+#[cfg(feature = "dcf77-pon")]
+use self::receiver_power::ReceiverPower;
 use self::{
-    dcf77::{Dcf77, Dcf77SignalVariant},
-    rtc::Rtc,
+    dcf77::{Dcf77, Dcf77Flags, Dcf77Frame, Dcf77Metrics, Dcf77SignalVariant, WorkflowError},
+    drift::DriftCalibration,
+    history::{Dcf77History, Dcf77Update},
+    reception::ReceptionHistogram,
+    rtc::{Alarm, Rtc, Temperature},
+    timer::Timer,
 };
-use crate::{Dcf77Input, ALARM_DAWN_DURATION_MINUTES, ALARM_WEEKEND_SUNRISE, ALARM_WEEK_SUNRISE};
+use crate::{
+    Dcf77Input, ALARM_DAWN_DURATION_MINUTES, ALARM_WEEKEND_SUNRISE, ALARM_WEEK_SUNRISE,
+    DCF77_UTC_OFFSET_VALIDITY_MINUTES, SUMMER_TIME_RULE,
+};
+#[cfg(feature = "dcf77-pon")]
+use crate::{Dcf77PowerOutput, DCF77_POWER_CYCLE_TIMEOUT_MINUTES, DCF77_POWER_WINDOW};
+pub use alarm_clock_decoder::{
+    datetime, dcf77, ClockDatetime, Date, Datetime, DayOfWeek, PhaseOfDay, SummerTimeRule, Time,
+    UtcOffset,
+};
+#[cfg(feature = "dcf77-pon")]
+use arduino_hal::port::PinMode;
 use arduino_hal::port::{mode::Io, Pin};
-pub use datetime::{Date, Datetime, DayOfWeek, PhaseOfDay, Time};
-use embedded_hal::blocking::i2c;
 
-pub mod datetime;
-pub mod dcf77;
+pub mod drift;
+pub mod history;
+#[cfg(feature = "dcf77-pon")]
+pub mod receiver_power;
+pub mod reception;
 pub mod rtc;
 pub mod timer;
 
 /// Main structure holding the current datetime
 /// and the interfaces to the DCF77 receiver and the RTC.
-pub struct Clock<I2C>
-where
-    I2C: i2c::Write + i2c::WriteRead,
-{
-    /// Current datetime
-    pub datetime: Option<Datetime>,
+pub struct Clock<RTC: Rtc> {
+    /// Current datetime, local time
+    pub datetime: ClockDatetime,
+    /// Current offset of the local time to UTC (the RTC holds UTC)
+    pub utc_offset: UtcOffset,
+    /// Rule of summer time, used to derive the offset to UTC
+    /// when no recent DCF77 update is available
+    pub summer_time_rule: SummerTimeRule,
     /// Time of the last DCF77 datetime update. In optimal
     /// conditions, an update is sent every minute.
     pub last_dcf77_update: Option<Datetime>,
+    /// Flags (announcements of a change of summer time or of a leap second)
+    /// of the last DCF77 datetime update.
+    pub last_dcf77_flags: Dcf77Flags,
     /// Is "some" when a DCF77 bit was received during this loop
     pub last_dcf77_bit: Option<Dcf77SignalVariant>,
+    /// Is "some" when the DCF77 decoder raised an error during this loop
+    pub last_dcf77_error: Option<WorkflowError>,
+    /// Is "some" when a new DCF77 polled value was processed during this loop
+    pub last_dcf77_sample: Option<(Timer, bool)>,
+    /// Temperature of the sensor of the RTC, read once per minute
+    pub temperature: Option<Temperature>,
     /// Phase of the day, used to determine if the alarm
     /// should be raised or not.
     pub phase_of_day: PhaseOfDay,
@@ -38,30 +67,76 @@ where
     /// Interface with the DFC77 receiver
     dcf77: Dcf77,
     /// Interface with the RTC
-    rtc: Rtc<I2C>,
+    rtc: RTC,
+    /// Time, in UTC, of the last DCF77 datetime update
+    last_dcf77_update_utc: Option<Datetime>,
+    /// Minute of the last reading of the temperature
+    temperature_minute: Option<u8>,
+    /// Times (UTC) of the week and week-end sunrises programmed
+    /// in the alarms of the RTC, if programmed
+    rtc_alarms: Option<(Option<Time>, Option<Time>)>,
+    /// Sunrise triggered by the alarm output of the RTC, until acked: timer of
+    /// the beginning of its current minute, and number of minutes elapsed
+    rtc_alarm_sunrise: Option<(Timer, u8)>,
+    /// Level of the alarm output of the RTC during the previous call
+    rtc_alarm_asserted: bool,
+    /// Last DCF77 updates of the RTC
+    dcf77_history: Dcf77History,
+    /// Calibration of the aging offset of the RTC
+    drift: DriftCalibration,
+    /// Histogram of the DCF77 reception by hour of the day
+    reception: ReceptionHistogram,
+    /// Power of the DCF77 receiver
+    #[cfg(feature = "dcf77-pon")]
+    receiver_power: ReceiverPower,
 }
 
-impl<I2C, CommE> Clock<I2C>
-where
-    I2C: i2c::Write<Error = CommE> + i2c::WriteRead<Error = CommE>,
-{
+impl<RTC: Rtc> Clock<RTC> {
     /// Initialize the structure with default settings.
-    pub fn init<MODE: Io>(
+    pub fn init<MODE: Io, #[cfg(feature = "dcf77-pon")] PONMODE: PinMode + Io>(
         tc0: arduino_hal::pac::TC0,
+        #[cfg(feature = "dcf77-int0")] exint: arduino_hal::pac::EXINT,
         pin: Pin<MODE, Dcf77Input>,
-        i2c: I2C,
+        #[cfg(feature = "dcf77-pon")] pon: Pin<PONMODE, Dcf77PowerOutput>,
+        rtc: RTC,
+        eeprom: arduino_hal::Eeprom,
     ) -> Self {
-        timer::init(tc0, pin);
+        timer::init(
+            tc0,
+            #[cfg(feature = "dcf77-int0")]
+            exint,
+            pin,
+        );
         Self {
-            datetime: None,
+            datetime: ClockDatetime::Unavailable,
+            utc_offset: SUMMER_TIME_RULE.standard,
+            summer_time_rule: SUMMER_TIME_RULE,
             last_dcf77_update: Default::default(),
+            last_dcf77_flags: Default::default(),
             last_dcf77_bit: None,
+            last_dcf77_error: None,
+            last_dcf77_sample: None,
+            temperature: None,
             phase_of_day: PhaseOfDay::Default { day_last_set: None },
             dawn_duration: Some(ALARM_DAWN_DURATION_MINUTES),
             week_sunrise: Some(ALARM_WEEK_SUNRISE),
             weekend_sunrise: Some(ALARM_WEEKEND_SUNRISE),
             dcf77: Default::default(),
-            rtc: Rtc::init(i2c),
+            rtc,
+            last_dcf77_update_utc: None,
+            temperature_minute: None,
+            rtc_alarms: None,
+            rtc_alarm_sunrise: None,
+            rtc_alarm_asserted: false,
+            dcf77_history: Default::default(),
+            drift: Default::default(),
+            reception: ReceptionHistogram::load(eeprom),
+            #[cfg(feature = "dcf77-pon")]
+            receiver_power: ReceiverPower::init(
+                pon,
+                DCF77_POWER_CYCLE_TIMEOUT_MINUTES,
+                DCF77_POWER_WINDOW,
+            ),
         }
     }
 
@@ -69,8 +144,15 @@ where
     pub fn update(&mut self) {
         let dcf77 = self.process_dcf77();
         self.process_rtc(dcf77);
+        self.process_temperature();
+        self.program_rtc_alarms();
+        self.reception
+            .record(self.datetime.trusted(), dcf77.is_some());
+        #[cfg(feature = "dcf77-pon")]
+        self.receiver_power
+            .update(self.datetime.trusted(), dcf77.is_some());
 
-        if let Some(datetime) = self.datetime {
+        if let Some(datetime) = self.datetime.trusted() {
             self.update_phase_of_day(datetime)
         }
     }
@@ -78,39 +160,318 @@ where
     /// Ack sunrise (the alarm), going back to the default phase of the day.
     pub fn ack_sunrise(&mut self) {
         self.phase_of_day = PhaseOfDay::Default {
-            day_last_set: self.datetime.map(|dt| dt.date.day),
+            day_last_set: self.datetime.trusted().map(|dt| dt.date.day),
+        };
+        self.rtc_alarm_sunrise = None;
+        self.rtc.clear_alarm_flags().ok();
+    }
+
+    /// Process the INT/SQW output of the RTC, asserted by its alarms,
+    /// as a trigger independent of the schedule of the software:
+    /// the sunrise comes if the alarm of the current day fired
+    /// (or if the RTC cannot be read) and was not acked yet today.
+    /// Only the assertion of the output triggers it, so that an output left
+    /// asserted (its flags could not be cleared) does not trigger it again after an ack.
+    pub fn process_rtc_alarm(&mut self, asserted: bool) {
+        self.advance_rtc_alarm_sunrise();
+        let triggered = asserted && !self.rtc_alarm_asserted;
+        self.rtc_alarm_asserted = asserted;
+        if !triggered {
+            return;
+        }
+        let is_due = match (self.rtc.alarm_flags(), self.datetime) {
+            (Ok((week, weekend)), ClockDatetime::Trusted(datetime)) => {
+                if datetime.date.weekday.is_week_end() {
+                    weekend
+                } else {
+                    week
+                }
+            }
+            // The alarms fire at random times when the RTC is wrong
+            (Ok(_), ClockDatetime::Untrusted(_)) => false,
+            // Without alarms, the output is not driven by the RTC
+            (Err(rtc::Error::Unsupported), _) => false,
+            _ => true,
+        };
+        if !is_due {
+            // Alarm of the other kind of day
+            self.rtc.clear_alarm_flags().ok();
+            return;
+        }
+        match self.phase_of_day {
+            // The flags are cleared by the ack
+            PhaseOfDay::SunRise { .. } => (),
+            PhaseOfDay::Default { day_last_set }
+                if day_last_set.is_some()
+                    && day_last_set == self.datetime.trusted().map(|dt| dt.date.day) =>
+            {
+                self.rtc.clear_alarm_flags().ok();
+            }
+            _ => {
+                self.phase_of_day = PhaseOfDay::SunRise {
+                    elapsed_since_sunrise: 0,
+                };
+                self.rtc_alarm_sunrise = timer::get_timer().map(|now| (now, 0));
+            }
+        }
+    }
+
+    /// Advance the elapsed time of a sunrise triggered by the alarm output
+    /// of the RTC with the internal timer, so that it is acked automatically
+    /// even without any trusted datetime.
+    fn advance_rtc_alarm_sunrise(&mut self) {
+        if let (Some((start, minutes)), Some(now)) = (self.rtc_alarm_sunrise, timer::get_timer()) {
+            let (start, minutes) = if now - start >= Timer(60_000) {
+                (start + Timer(60_000), minutes.saturating_add(1))
+            } else {
+                (start, minutes)
+            };
+            self.rtc_alarm_sunrise = Some((start, minutes));
+            if let PhaseOfDay::SunRise {
+                elapsed_since_sunrise,
+            } = &mut self.phase_of_day
+            {
+                *elapsed_since_sunrise = (*elapsed_since_sunrise).max(minutes);
+            }
         }
     }
 
     /// Run dcf77 decoder, waiting for a new update.
-    fn process_dcf77(&mut self) -> Option<Datetime> {
-        match self.dcf77.run() {
+    fn process_dcf77(&mut self) -> Option<Dcf77Frame> {
+        self.last_dcf77_error = None;
+        #[cfg(not(feature = "dcf77-int0"))]
+        let result = self
+            .dcf77
+            .run(timer::get_polled_values(), self.datetime.trusted());
+        #[cfg(feature = "dcf77-int0")]
+        let result = self
+            .dcf77
+            .run_edge(timer::get_edge(), self.datetime.trusted());
+        #[cfg(not(feature = "dcf77-int0"))]
+        self.last_dcf77_sample = self.dcf77.last_sample();
+        if let Some(correction) = self.dcf77.take_timer_correction() {
+            timer::set_correction(correction);
+        }
+        match result {
             Ok((bit, None)) => {
                 self.last_dcf77_bit = bit;
                 // No update
                 None
             }
-            Ok((bit, Some(dcf77_datetime))) => {
+            Ok((bit, Some(dcf77_frame))) => {
                 self.last_dcf77_bit = bit;
                 // New datetime from dcf77
-                Some(dcf77_datetime)
+                Some(dcf77_frame)
             }
-            Err(_) => {
-                // Reset the internal state of the dcf77 decoder
-                self.dcf77 = Default::default();
+            Err(error) => {
+                // The decoder keeps its internal state, the affected bits
+                // being marked as unknown.
+                self.last_dcf77_bit = None;
+                self.last_dcf77_error = Some(error);
                 None
             }
         }
     }
 
-    /// Set the rtc with a dcf77 update if given,
-    /// and in any case read the updated value.
-    fn process_rtc(&mut self, dcf77: Option<Datetime>) {
+    /// Number of DCF77 frames rejected because they were not
+    /// consistent with the previous one nor with the RTC.
+    pub fn dcf77_rejected_frames(&self) -> u16 {
+        self.dcf77.rejected_frames()
+    }
+
+    /// Metrics of the quality of the DCF77 reception.
+    pub fn dcf77_metrics(&self) -> &Dcf77Metrics {
+        self.dcf77.metrics()
+    }
+
+    /// Last DCF77 updates of the RTC, along with the time of the RTC before each one.
+    pub fn dcf77_history(&self) -> &Dcf77History {
+        &self.dcf77_history
+    }
+
+    /// Last measures of the drift of the RTC, along with the aging offset applied.
+    pub fn drift(&self) -> &DriftCalibration {
+        &self.drift
+    }
+
+    /// Set the number of minutes without any DCF77 frame before
+    /// power-cycling the receiver, if any.
+    #[cfg(feature = "dcf77-pon")]
+    pub fn set_dcf77_power_timeout(&mut self, timeout_minutes: Option<u16>) {
+        self.receiver_power.set_timeout(timeout_minutes);
+    }
+
+    /// Set the window of the day (local time) when the DCF77 receiver
+    /// is powered, if any: beginning and end, which may be on the next day.
+    #[cfg(feature = "dcf77-pon")]
+    pub fn set_dcf77_power_window(&mut self, window: Option<(Time, Time)>) {
+        self.receiver_power.set_window(window);
+    }
+
+    /// Number of minutes without any DCF77 frame before power-cycling
+    /// the receiver, if any.
+    #[cfg(feature = "dcf77-pon")]
+    pub fn dcf77_power_timeout(&self) -> Option<u16> {
+        self.receiver_power.timeout()
+    }
+
+    /// Window of the day (local time) when the DCF77 receiver is powered, if any.
+    #[cfg(feature = "dcf77-pon")]
+    pub fn dcf77_power_window(&self) -> Option<(Time, Time)> {
+        self.receiver_power.window()
+    }
+
+    /// Return true if the DCF77 receiver is powered.
+    #[cfg(feature = "dcf77-pon")]
+    pub fn is_dcf77_receiver_on(&self) -> bool {
+        self.receiver_power.is_on()
+    }
+
+    /// Histogram of the DCF77 reception by hour of the day.
+    pub fn reception(&self) -> &ReceptionHistogram {
+        &self.reception
+    }
+
+    /// Set the rtc (in UTC) with a dcf77 update if given,
+    /// and in any case read the updated value, converted to local time.
+    fn process_rtc(&mut self, dcf77: Option<Dcf77Frame>) {
         if let Some(dcf77) = dcf77 {
-            self.last_dcf77_update = Some(dcf77);
-            self.rtc.set_datetime(&dcf77).ok();
+            self.last_dcf77_update = Some(dcf77.datetime);
+            self.last_dcf77_flags = dcf77.flags;
+            self.utc_offset = dcf77.utc_offset;
+            let utc = dcf77.datetime.to_utc(dcf77.utc_offset);
+            self.last_dcf77_update_utc = Some(utc);
+            let rtc = match self.rtc.oscillator_stopped() {
+                Ok(false) => self.rtc.datetime().ok(),
+                _ => None,
+            };
+            let update = Dcf77Update { dcf77: utc, rtc };
+            self.dcf77_history.push(update);
+            // The RTC is set only when it differs, so that its drift
+            // accumulates and can be measured.
+            match update.correction_s() {
+                Some(0) => self.drift.confirm(utc),
+                correction => {
+                    let aging_offset = self.rtc.aging_offset().ok();
+                    let set = self.rtc.set_datetime(&utc).is_ok();
+                    match (correction, aging_offset) {
+                        (Some(correction), Some(aging_offset)) if set => {
+                            if let Some(aging_offset) =
+                                self.drift.measure(utc, -correction, aging_offset)
+                            {
+                                self.rtc.set_aging_offset(aging_offset).ok();
+                            }
+                        }
+                        _ => self.drift.reset(set.then_some(utc)),
+                    }
+                }
+            }
+        }
+        let utc = self.rtc.datetime().ok();
+        if let Some(utc) = utc {
+            self.update_utc_offset(utc);
+        }
+        // The flag is cleared only when the RTC is set
+        let stopped = self.rtc.oscillator_stopped().unwrap_or(true);
+        self.datetime = match utc.map(|utc| utc.to_local(self.utc_offset)) {
+            Some(datetime) if stopped => ClockDatetime::Untrusted(datetime),
+            Some(datetime) => ClockDatetime::Trusted(datetime),
+            None => ClockDatetime::Unavailable,
+        };
+    }
+
+    /// Set the RTC by hand, with a local datetime, the offset to UTC
+    /// being derived from the rule of summer time.
+    pub fn set_datetime(&mut self, datetime: Datetime) {
+        let utc_offset = self
+            .summer_time_rule
+            .utc_offset(&datetime.to_utc(self.summer_time_rule.standard));
+        // Not as exact as DCF77: no reference for the drift
+        self.drift.reset(None);
+        self.rtc.set_datetime(&datetime.to_utc(utc_offset)).ok();
+    }
+
+    /// Read the temperature of the RTC once per minute, and force a new
+    /// conversion, read the next minute.
+    fn process_temperature(&mut self) {
+        let minute = self
+            .datetime
+            .unchecked()
+            .map(|datetime| datetime.time.minute);
+        if minute != self.temperature_minute {
+            self.temperature_minute = minute;
+            self.temperature = self.rtc.temperature().ok();
+            self.rtc.convert_temperature().ok();
+        }
+    }
+
+    /// Program the sunrises, converted to UTC, in the alarms of the RTC
+    /// (alarm 1 for the week, alarm 2 for the week-end),
+    /// whenever they or the offset to UTC change.
+    fn program_rtc_alarms(&mut self) {
+        if let ClockDatetime::Untrusted(_) = self.datetime {
+            // The registers of the alarms may have been lost as well
+            self.rtc_alarms = None;
+            return;
+        }
+        let to_utc = |time: Option<Time>| {
+            time.map(|time| {
+                let minutes = 60 * time.hour as i16 + time.minute as i16;
+                let minutes = (minutes - self.utc_offset.minutes()).rem_euclid(24 * 60);
+                Time {
+                    hour: (minutes / 60) as u8,
+                    minute: (minutes % 60) as u8,
+                    second: None,
+                }
+            })
+        };
+        let alarms = (to_utc(self.week_sunrise), to_utc(self.weekend_sunrise));
+        if self.rtc_alarms != Some(alarms) {
+            // The alarms missing from the RTC are left to the software.
+            let done = |result| matches!(result, Ok(()) | Err(rtc::Error::Unsupported));
+            // Flags set before (re)programming are stale
+            let programmed = done(self.rtc.set_alarm(Alarm::One, alarms.0))
+                && done(self.rtc.set_alarm(Alarm::Two, alarms.1))
+                && done(self.rtc.clear_alarm_flags());
+            self.rtc_alarms = programmed.then_some(alarms);
+        }
+    }
+
+    /// Keep the offset given by DCF77 if recent enough,
+    /// otherwise derive it from the rule of summer time.
+    fn update_utc_offset(&mut self, utc: Datetime) {
+        let dcf77_is_recent = self
+            .last_dcf77_update_utc
+            .and_then(|last_dcf77_update_utc| utc - last_dcf77_update_utc)
+            .map(|diff| diff < DCF77_UTC_OFFSET_VALIDITY_MINUTES)
+            .unwrap_or(false);
+
+        if dcf77_is_recent {
+            self.apply_announced_summer_time_change(utc);
+        } else {
+            self.utc_offset = self.summer_time_rule.utc_offset(&utc);
+        }
+    }
+
+    /// A change between standard and summer time (CET and CEST) is announced
+    /// by DCF77 during the hour before the change. If no update was received
+    /// since then, apply the change anyway at the beginning of the next hour.
+    fn apply_announced_summer_time_change(&mut self, utc: Datetime) {
+        if !self.last_dcf77_flags.summer_time_announcement {
+            return;
+        }
+        if let Some(last_dcf77_update_utc) = self.last_dcf77_update_utc {
+            if utc.date != last_dcf77_update_utc.date
+                || utc.time.hour != last_dcf77_update_utc.time.hour
+            {
+                self.utc_offset = if self.utc_offset == self.summer_time_rule.summer {
+                    self.summer_time_rule.standard
+                } else {
+                    self.summer_time_rule.summer
+                };
+                self.last_dcf77_flags.summer_time_announcement = false;
+            }
         }
-        self.datetime = self.rtc.datetime().ok();
     }
 
     /// Determine if the phase of the day must be updated, that is,
@@ -160,7 +521,9 @@ where
 
     /// Compute the number of quarter jours since the last dcf77 update.
     pub fn quarters_since_last_rtc_update(&self) -> Option<u8> {
-        if let (Some(datetime), Some(last_dcf77_update)) = (self.datetime, self.last_dcf77_update) {
+        if let (Some(datetime), Some(last_dcf77_update)) =
+            (self.datetime.trusted(), self.last_dcf77_update)
+        {
             let diff = core::cmp::max((datetime - last_dcf77_update)?, 0);
             Some((diff / 15) as u8)
         } else {
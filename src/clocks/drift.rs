@@ -0,0 +1,135 @@
+//! Calibration of the aging offset of the RTC from its drift:
+//! the RTC is set only when it differs from a DCF77 update, so that
+//! its error accumulates over its settings, the drift being the error
+//! over the elapsed time. The aging offset is then adjusted in a closed
+//! loop, by bounded steps, to keep the RTC accurate without DCF77.
+use super::{Datetime, Time};
+use crate::RTC_DRIFT_HISTORY_LENGTH;
+use ringbuffer::{ConstGenericRingBuffer, RingBuffer};
+
+/// Largest error, in seconds, at a setting of the RTC taken as a drift:
+/// a larger one rather comes from a reset of the RTC
+const MAX_ERROR_S: i32 = 2;
+/// Smallest error, in seconds, accumulated before the drift is measured,
+/// each setting being rounded to the second
+const MIN_ERROR_S: i32 = 5;
+/// Largest change of the aging offset after a measure
+/// (one unit is about 0.1 ppm)
+const MAX_STEP: i32 = 10;
+
+/// Measure of the drift of the RTC
+#[derive(Clone, Copy)]
+pub struct DriftMeasure {
+    /// Datetime of the DCF77 update, in UTC
+    pub utc: Datetime,
+    /// Number of seconds since the previous measure, or since the RTC was reset
+    pub elapsed_s: i32,
+    /// Error of the RTC accumulated over its settings, in seconds, positive if ahead
+    pub error_s: i32,
+    /// Aging offset applied after the measure
+    pub aging_offset: i8,
+}
+
+impl DriftMeasure {
+    /// Drift of the RTC, in tenths of ppm, positive if fast.
+    pub fn drift_ppm_tenths(&self) -> i32 {
+        self.error_s * 10_000_000 / self.elapsed_s
+    }
+}
+
+impl ufmt::uDisplay for DriftMeasure {
+    /// Format a measure to display on the serial port,
+    /// for instance 2023-12-07T21:34:00 1440 min 1 s 11.5 ppm aging 6
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        self.utc.fmt(f)?;
+        f.write_char(' ')?;
+        (self.elapsed_s / 60).fmt(f)?;
+        f.write_str(" min ")?;
+        self.error_s.fmt(f)?;
+        f.write_str(" s ")?;
+        let drift = self.drift_ppm_tenths();
+        if drift < 0 {
+            f.write_char('-')?;
+        }
+        (drift.unsigned_abs() / 10).fmt(f)?;
+        f.write_char('.')?;
+        (drift.unsigned_abs() % 10).fmt(f)?;
+        f.write_str(" ppm aging ")?;
+        self.aging_offset.fmt(f)
+    }
+}
+
+/// Closed loop on the aging offset of the RTC
+#[derive(Default)]
+pub struct DriftCalibration {
+    /// Datetime (UTC) since which the error of the RTC is known, if any
+    reference: Option<Datetime>,
+    /// Error of the RTC accumulated since the reference, in seconds,
+    /// positive if ahead
+    error_s: i32,
+    /// Last measures, the oldest first
+    history: ConstGenericRingBuffer<DriftMeasure, RTC_DRIFT_HISTORY_LENGTH>,
+}
+
+impl DriftCalibration {
+    /// Record that the RTC agrees with a DCF77 update, the reference
+    /// of the next measure unless one is already known.
+    pub fn confirm(&mut self, utc: Datetime) {
+        self.reference.get_or_insert(utc);
+    }
+
+    /// Record that the RTC was reset, at a datetime (UTC) if exact.
+    pub fn reset(&mut self, utc: Option<Datetime>) {
+        self.reference = utc;
+        self.error_s = 0;
+    }
+
+    /// Record the error of the RTC, in seconds, at a DCF77 update which set it,
+    /// and return the aging offset to apply, given the current one, once the
+    /// accumulated error is large enough to measure the drift.
+    pub fn measure(&mut self, utc: Datetime, error_s: i32, aging_offset: i8) -> Option<i8> {
+        let reference = match self.reference {
+            Some(reference) if error_s.abs() <= MAX_ERROR_S => reference,
+            _ => {
+                self.reset(Some(utc));
+                return None;
+            }
+        };
+        self.error_s += error_s;
+        if self.error_s.abs() < MIN_ERROR_S {
+            return None;
+        }
+        let mut measure = DriftMeasure {
+            utc,
+            elapsed_s: elapsed_s(reference, utc),
+            error_s: self.error_s,
+            aging_offset,
+        };
+        // One unit of the aging offset is about 0.1 ppm: apply half
+        // of the correction, the error being rounded to the second,
+        // by a bounded step.
+        let correction = (measure.drift_ppm_tenths() / 2).clamp(-MAX_STEP, MAX_STEP);
+        measure.aging_offset =
+            (aging_offset as i32 + correction).clamp(i8::MIN as i32, i8::MAX as i32) as i8;
+        self.history.push(measure);
+        self.reset(Some(utc));
+        Some(measure.aging_offset)
+    }
+
+    /// Iterate over the measures, the oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &DriftMeasure> {
+        self.history.iter()
+    }
+}
+
+/// Number of seconds between two datetimes, which may be farther apart
+/// than the minutes between them fit in an i16 (about 22 days).
+fn elapsed_s(from: Datetime, to: Datetime) -> i32 {
+    let seconds = |time: Time| {
+        3600 * time.hour as i32 + 60 * time.minute as i32 + time.second.unwrap_or(0) as i32
+    };
+    86_400 * (to.date - from.date) as i32 + seconds(to.time) - seconds(from.time)
+}
@@ -0,0 +1,64 @@
+//! History of the last DCF77 updates of the RTC, to tell a jump
+//! of the RTC caused by a bad frame from a real correction.
+use super::Datetime;
+use crate::DCF77_HISTORY_LENGTH;
+use ringbuffer::{ConstGenericRingBuffer, RingBuffer};
+
+/// DCF77 update of the RTC
+#[derive(Clone, Copy)]
+pub struct Dcf77Update {
+    /// Datetime of the published frame, in UTC
+    pub dcf77: Datetime,
+    /// Datetime of the RTC just before the update, in UTC,
+    /// if it could be read and trusted
+    pub rtc: Option<Datetime>,
+}
+
+impl Dcf77Update {
+    /// Correction applied to the RTC, in seconds, if known.
+    pub fn correction_s(&self) -> Option<i32> {
+        let rtc = self.rtc?;
+        let minutes = (self.dcf77 - rtc)? as i32;
+        let seconds = self.dcf77.time.second.unwrap_or_default() as i32
+            - rtc.time.second.unwrap_or_default() as i32;
+        Some(60 * minutes + seconds)
+    }
+}
+
+impl ufmt::uDisplay for Dcf77Update {
+    /// Format an update to display on the serial port,
+    /// for instance 2023-12-07T21:34:00 rtc 2023-12-07T21:34:02 -2 s
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        self.dcf77.fmt(f)?;
+        f.write_str(" rtc ")?;
+        match (self.rtc, self.correction_s()) {
+            (Some(rtc), Some(correction)) => {
+                rtc.fmt(f)?;
+                f.write_char(' ')?;
+                correction.fmt(f)?;
+                f.write_str(" s")
+            }
+            (Some(rtc), None) => rtc.fmt(f),
+            (None, _) => f.write_str("None"),
+        }
+    }
+}
+
+/// Last DCF77 updates of the RTC, the oldest first
+#[derive(Default)]
+pub struct Dcf77History(ConstGenericRingBuffer<Dcf77Update, DCF77_HISTORY_LENGTH>);
+
+impl Dcf77History {
+    /// Record an update, dropping the oldest one if full.
+    pub fn push(&mut self, update: Dcf77Update) {
+        self.0.push(update);
+    }
+
+    /// Iterate over the updates, the oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &Dcf77Update> {
+        self.0.iter()
+    }
+}
@@ -0,0 +1,164 @@
+//! Power management of the DCF77 receiver through its PON (power-on) pin:
+//! the receiver is power-cycled after some time without any frame
+//! (some modules latch into a bad state of their automatic gain control),
+//! and may be powered only during a window of the day, for instance at night.
+use super::{
+    timer::{get_timer, Timer},
+    Datetime, Time,
+};
+use crate::{Dcf77PowerOutput, DCF77_PON_LOGICAL_LEVEL_HIGH};
+use arduino_hal::port::{
+    mode::{Io, Output},
+    Pin, PinMode,
+};
+
+/// Duration, in ms, during which the receiver is off when power-cycled
+const POWER_CYCLE_OFF_MS: u16 = 2000;
+
+/// State of the power of the receiver
+#[derive(Clone, Copy)]
+enum State {
+    /// Powered, since a frame or the power-on: timer of the beginning of the current
+    /// minute and number of minutes elapsed, counted once the timer runs
+    /// (the datetime may not be trusted while no frame is received)
+    On(Option<(Timer, u16)>),
+    /// Off while power-cycled, since a timer
+    Cycling(Option<Timer>),
+    /// Off outside of the window
+    Off,
+}
+
+/// Control of the power of the receiver
+pub struct ReceiverPower {
+    /// Pin connected to the PON input of the receiver
+    pon: Pin<Output, Dcf77PowerOutput>,
+    /// Number of minutes without any frame before power-cycling the receiver
+    timeout_minutes: Option<u16>,
+    /// Window of the day (local time), beginning and end, when the receiver is powered
+    window: Option<(Time, Time)>,
+    state: State,
+}
+
+impl ReceiverPower {
+    /// Initialize the object, the receiver being powered.
+    pub fn init<MODE: PinMode + Io>(
+        pon: Pin<MODE, Dcf77PowerOutput>,
+        timeout_minutes: Option<u16>,
+        window: Option<(Time, Time)>,
+    ) -> Self {
+        let mut out = Self {
+            pon: pon.into_output(),
+            timeout_minutes,
+            window,
+            state: State::On(None),
+        };
+        out.power(true);
+        out
+    }
+
+    /// Set the number of minutes without any frame before power-cycling
+    /// the receiver, if any.
+    pub fn set_timeout(&mut self, timeout_minutes: Option<u16>) {
+        self.timeout_minutes = timeout_minutes;
+    }
+
+    /// Set the window of the day when the receiver is powered, if any
+    /// (the end may be on the next day).
+    pub fn set_window(&mut self, window: Option<(Time, Time)>) {
+        self.window = window;
+    }
+
+    /// Number of minutes without any frame before power-cycling the receiver, if any.
+    pub fn timeout(&self) -> Option<u16> {
+        self.timeout_minutes
+    }
+
+    /// Window of the day when the receiver is powered, if any.
+    pub fn window(&self) -> Option<(Time, Time)> {
+        self.window
+    }
+
+    /// Return true if the receiver is powered.
+    pub fn is_on(&self) -> bool {
+        matches!(self.state, State::On(_))
+    }
+
+    /// Update the power of the receiver, given the current local datetime,
+    /// if any, and whether a frame was just published: to be called
+    /// at least once a minute, the minutes without any frame being counted
+    /// with the internal timer.
+    pub fn update(&mut self, datetime: Option<Datetime>, frame_published: bool) {
+        let in_window = match (self.window, datetime) {
+            (Some(window), Some(datetime)) => Self::is_within(datetime.time, window),
+            _ => true,
+        };
+        self.state = match self.state {
+            State::On(_) if !in_window => {
+                self.power(false);
+                State::Off
+            }
+            State::On(since) => {
+                let since = match (since, get_timer()) {
+                    (_, now) if frame_published => now.map(|now| (now, 0)),
+                    (Some((start, minutes)), Some(now)) if now - start >= Timer(60_000) => {
+                        Some((start + Timer(60_000), minutes.saturating_add(1)))
+                    }
+                    (None, now) => now.map(|now| (now, 0)),
+                    (since, _) => since,
+                };
+                let timed_out = match (self.timeout_minutes, since) {
+                    (Some(timeout), Some((_, minutes))) => minutes >= timeout,
+                    _ => false,
+                };
+                if timed_out {
+                    self.power(false);
+                    State::Cycling(get_timer())
+                } else {
+                    State::On(since)
+                }
+            }
+            State::Cycling(since) => {
+                let elapsed = match (get_timer(), since) {
+                    (Some(now), Some(since)) => (now - since).0 >= POWER_CYCLE_OFF_MS,
+                    _ => true,
+                };
+                if elapsed {
+                    self.power(in_window);
+                    if in_window {
+                        State::On(None)
+                    } else {
+                        State::Off
+                    }
+                } else {
+                    State::Cycling(since)
+                }
+            }
+            State::Off if in_window => {
+                self.power(true);
+                State::On(None)
+            }
+            State::Off => State::Off,
+        };
+    }
+
+    /// Return true if a time is within a window, which may end on the next day.
+    fn is_within(time: Time, (start, end): (Time, Time)) -> bool {
+        let minutes = |time: Time| 60 * time.hour as u16 + time.minute as u16;
+        let (time, start, end) = (minutes(time), minutes(start), minutes(end));
+        if start <= end {
+            start <= time && time < end
+        } else {
+            start <= time || time < end
+        }
+    }
+
+    /// Set the pin to the appropriate electrical level
+    /// to power the receiver on or off.
+    fn power(&mut self, on: bool) {
+        if on == DCF77_PON_LOGICAL_LEVEL_HIGH {
+            self.pon.set_high();
+        } else {
+            self.pon.set_low();
+        }
+    }
+}
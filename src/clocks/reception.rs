@@ -0,0 +1,128 @@
+//! Histogram of the DCF77 reception by hour of the day (local time):
+//! each minute of the RTC is counted as a success if a frame was published
+//! during it, as a failure otherwise.
+//! The counts are persisted in the EEPROM at the end of each hour,
+//! so that at most the counts of the current hour are lost by a reset.
+use super::Datetime;
+use arduino_hal::Eeprom;
+
+/// Offset of the histogram in the EEPROM
+const EEPROM_OFFSET: u16 = 0;
+/// Marker written before the counts, telling an initialized EEPROM
+/// from an erased one (filled with 0xFF)
+const EEPROM_MARKER: u8 = 0x77;
+/// Size, in bytes, of the counts of an hour in the EEPROM
+const HOUR_COUNTS_SIZE: u16 = 4;
+
+/// Counts of the minutes of an hour of the day
+#[derive(Default, Clone, Copy)]
+pub struct HourCounts {
+    /// Minutes during which a frame was published
+    pub successes: u16,
+    /// Minutes during which no frame was published
+    pub failures: u16,
+}
+
+impl HourCounts {
+    /// Count a minute; both counts are halved before saturating,
+    /// to keep their ratio.
+    fn record(&mut self, success: bool) {
+        if self.successes == u16::MAX || self.failures == u16::MAX {
+            self.successes /= 2;
+            self.failures /= 2;
+        }
+        if success {
+            self.successes += 1;
+        } else {
+            self.failures += 1;
+        }
+    }
+
+    fn to_bytes(self) -> [u8; HOUR_COUNTS_SIZE as usize] {
+        let [s0, s1] = self.successes.to_le_bytes();
+        let [f0, f1] = self.failures.to_le_bytes();
+        [s0, s1, f0, f1]
+    }
+
+    fn from_bytes([s0, s1, f0, f1]: [u8; HOUR_COUNTS_SIZE as usize]) -> Self {
+        Self {
+            successes: u16::from_le_bytes([s0, s1]),
+            failures: u16::from_le_bytes([f0, f1]),
+        }
+    }
+}
+
+/// Histogram of the reception, persisted in the EEPROM
+pub struct ReceptionHistogram {
+    eeprom: Eeprom,
+    /// Counts by hour of the day
+    hours: [HourCounts; 24],
+    /// Hour and minute of the RTC during the last call, if any
+    current_minute: Option<(u8, u8)>,
+    /// A frame was published during the current minute
+    frame_published: bool,
+}
+
+impl ReceptionHistogram {
+    /// Load the histogram from the EEPROM, or initialize it if erased.
+    pub fn load(mut eeprom: Eeprom) -> Self {
+        let mut hours = [HourCounts::default(); 24];
+        if eeprom.read_byte(EEPROM_OFFSET) == EEPROM_MARKER {
+            for (hour, counts) in hours.iter_mut().enumerate() {
+                let mut bytes = [0; HOUR_COUNTS_SIZE as usize];
+                eeprom.read(Self::hour_offset(hour as u8), &mut bytes).ok();
+                *counts = HourCounts::from_bytes(bytes);
+            }
+        } else {
+            for hour in 0..24 {
+                eeprom
+                    .write(Self::hour_offset(hour), &HourCounts::default().to_bytes())
+                    .ok();
+            }
+            eeprom.write_byte(EEPROM_OFFSET, EEPROM_MARKER);
+        }
+        Self {
+            eeprom,
+            hours,
+            current_minute: None,
+            frame_published: false,
+        }
+    }
+
+    /// Record the current local datetime, if any, and whether a frame
+    /// was just published: when the minute changes, the previous one
+    /// is counted (the frame ending a minute being published
+    /// at the same time as the change).
+    pub fn record(&mut self, datetime: Option<Datetime>, frame_published: bool) {
+        self.frame_published |= frame_published;
+        if let Some(datetime) = datetime {
+            let minute = (datetime.time.hour, datetime.time.minute);
+            if let Some((hour, previous_minute)) = self.current_minute {
+                if (hour, previous_minute) != minute {
+                    self.hours[hour as usize].record(self.frame_published);
+                    self.frame_published = false;
+                    if hour != minute.0 {
+                        self.save(hour);
+                    }
+                }
+            }
+            self.current_minute = Some(minute);
+        }
+    }
+
+    /// Counts by hour of the day.
+    pub fn hours(&self) -> &[HourCounts; 24] {
+        &self.hours
+    }
+
+    /// Write the counts of an hour to the EEPROM.
+    fn save(&mut self, hour: u8) {
+        let bytes = self.hours[hour as usize].to_bytes();
+        self.eeprom.write(Self::hour_offset(hour), &bytes).ok();
+    }
+
+    /// Offset of the counts of an hour in the EEPROM.
+    fn hour_offset(hour: u8) -> u16 {
+        EEPROM_OFFSET + 1 + HOUR_COUNTS_SIZE * hour as u16
+    }
+}
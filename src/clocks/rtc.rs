@@ -1,9 +1,32 @@
-//! DS3231 RTC Interface
-// https://www.analog.com/media/en/technical-documentation/data-sheets/ds3231.pdf
+//! Interface with the RTC, holding the datetime in UTC: a DS3231 by default,
+//! or a DS1307 or a PCF8563, selected by a cargo feature.
+//!
+//! Each device handles its own registers; the capabilities missing
+//! from a device (temperature, aging offset, alarms) return `Error::Unsupported`.
 
-use super::{Date, Datetime, Time};
+use super::{Datetime, Time};
 use embedded_hal::blocking::i2c;
 
+#[cfg(feature = "ds1307")]
+pub mod ds1307;
+#[cfg(not(any(feature = "ds1307", feature = "pcf8563")))]
+pub mod ds3231;
+#[cfg(feature = "pcf8563")]
+pub mod pcf8563;
+
+#[cfg(all(feature = "ds1307", feature = "pcf8563"))]
+compile_error!("Only one of the features `ds1307` and `pcf8563` can be enabled");
+
+/// RTC, selected by the cargo features
+#[cfg(not(any(feature = "ds1307", feature = "pcf8563")))]
+pub type SelectedRtc<I2C> = ds3231::Ds3231<I2C>;
+/// RTC, selected by the cargo features
+#[cfg(feature = "ds1307")]
+pub type SelectedRtc<I2C> = ds1307::Ds1307<I2C>;
+/// RTC, selected by the cargo features
+#[cfg(feature = "pcf8563")]
+pub type SelectedRtc<I2C> = pcf8563::Pcf8563<I2C>;
+
 /// Variants of enums
 #[derive(Debug)]
 pub enum Error<CommE> {
@@ -11,9 +34,12 @@ pub enum Error<CommE> {
     Comm(CommE),
     /// Invalid input data provided
     InvalidInputData,
+    /// Capability not available on the device
+    Unsupported,
 }
 
 /// Hours in either 12-hour (AM/PM) or 24-hour format
+#[cfg(not(feature = "pcf8563"))]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Hours {
     /// AM [1-12]
@@ -24,115 +50,136 @@ pub enum Hours {
     H24(u8),
 }
 
-/// Temperature conversion rate
-///
-/// This is only available on the DS3232 and DS3234 devices.
+/// Alarms of the device
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub enum TempConvRate {
-    /// Once every 64 seconds (default)
-    _64s,
-    /// Once every 128 seconds
-    _128s,
-    /// Once every 256 seconds
-    _256s,
-    /// Once every 512 seconds
-    _512s,
+pub enum Alarm {
+    /// Alarm 1 (the only alarm of the PCF8563)
+    One,
+    /// Alarm 2
+    Two,
 }
 
-struct Register;
+/// Temperature, in quarters of degree Celsius
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Temperature(pub i16);
 
-impl Register {
-    const SECONDS: u8 = 0x00;
-    const MINUTES: u8 = 0x01;
-    const HOURS: u8 = 0x02;
-    const DOW: u8 = 0x03;
-    const DOM: u8 = 0x04;
-    const MONTH: u8 = 0x05;
-    const YEAR: u8 = 0x06;
+impl Temperature {
+    /// Temperature rounded to the nearest degree Celsius.
+    pub fn degrees(&self) -> i16 {
+        (self.0 + 2).div_euclid(4)
+    }
+}
+
+impl ufmt::uDisplay for Temperature {
+    /// Format a temperature to display on the serial port.
+    /// for instance -3.25
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        if self.0 < 0 {
+            f.write_str("-")?;
+        }
+        let quarters = self.0.unsigned_abs();
+        (quarters / 4).fmt(f)?;
+        match quarters % 4 {
+            0 => f.write_str(".00"),
+            1 => f.write_str(".25"),
+            2 => f.write_str(".50"),
+            _ => f.write_str(".75"),
+        }
+    }
 }
 
+/// Real-time clock
+pub trait Rtc {
+    /// Error of the bus
+    type CommE;
+
+    /// Read the datetime.
+    fn datetime(&mut self) -> Result<Datetime, Error<Self::CommE>>;
+
+    /// Set the datetime, clearing the oscillator stop flag.
+    fn set_datetime(&mut self, datetime: &Datetime) -> Result<(), Error<Self::CommE>>;
+
+    /// Return true if the oscillator stopped since the datetime was last set
+    /// (at the first power-up, or if the battery failed): the datetime is wrong.
+    fn oscillator_stopped(&mut self) -> Result<bool, Error<Self::CommE>>;
+
+    /// Read the temperature of the sensor.
+    fn temperature(&mut self) -> Result<Temperature, Error<Self::CommE>> {
+        Err(Error::Unsupported)
+    }
+
+    /// Force a conversion of the temperature, unless one is already
+    /// in progress: return true if started.
+    fn convert_temperature(&mut self) -> Result<bool, Error<Self::CommE>> {
+        Err(Error::Unsupported)
+    }
+
+    /// Read the aging offset, in units of about 0.1 ppm, slowing the oscillator.
+    fn aging_offset(&mut self) -> Result<i8, Error<Self::CommE>> {
+        Err(Error::Unsupported)
+    }
+
+    /// Write the aging offset.
+    fn set_aging_offset(&mut self, _aging_offset: i8) -> Result<(), Error<Self::CommE>> {
+        Err(Error::Unsupported)
+    }
+
+    /// Program an alarm to fire every day at a given time (the second
+    /// being ignored), asserting the interrupt output, or disable it.
+    fn set_alarm(&mut self, _alarm: Alarm, _time: Option<Time>) -> Result<(), Error<Self::CommE>> {
+        Err(Error::Unsupported)
+    }
+
+    /// Read the flags of the alarms which fired, alarm 1 and alarm 2.
+    fn alarm_flags(&mut self) -> Result<(bool, bool), Error<Self::CommE>> {
+        Err(Error::Unsupported)
+    }
+
+    /// Clear the flags of the alarms, releasing the interrupt output.
+    fn clear_alarm_flags(&mut self) -> Result<(), Error<Self::CommE>> {
+        Err(Error::Unsupported)
+    }
+}
+
+/// Flags of the hours register, in the format of the DS1307 and DS3231
+#[cfg(not(feature = "pcf8563"))]
 struct BitFlags;
 
+#[cfg(not(feature = "pcf8563"))]
 impl BitFlags {
     const H24_H12: u8 = 0b0100_0000;
     const AM_PM: u8 = 0b0010_0000;
-    const CENTURY: u8 = 0b1000_0000;
 }
 
-const DEVICE_ADDRESS: u8 = 0b110_1000;
-
-#[derive(Debug, Default)]
-pub struct Rtc<I2C>
+/// Write registers via the I2C interface, the address of the first one
+/// being the first byte of the payload.
+fn write_registers<I2C, CommE>(
+    i2c: &mut I2C,
+    device_address: u8,
+    payload: &[u8],
+) -> Result<(), Error<CommE>>
 where
-    I2C: i2c::Write + i2c::WriteRead,
+    I2C: i2c::Write<Error = CommE>,
 {
-    i2c: I2C,
+    i2c.write(device_address, payload).map_err(Error::Comm)
 }
 
-impl<I2C, CommE> Rtc<I2C>
+/// Read registers via the I2C interface, the address of the first one
+/// being the first byte of the payload, filled with the values.
+fn read_registers<I2C, CommE>(
+    i2c: &mut I2C,
+    device_address: u8,
+    payload: &mut [u8],
+) -> Result<(), Error<CommE>>
 where
-    I2C: i2c::Write<Error = CommE> + i2c::WriteRead<Error = CommE>,
+    I2C: i2c::WriteRead<Error = CommE>,
 {
-    /// Create a new instance of the DS3231 device.
-    pub fn init(i2c: I2C) -> Self {
-        Rtc { i2c }
-    }
-
-    pub fn datetime(&mut self) -> Result<Datetime, Error<CommE>> {
-        let mut data = [0; 8];
-        self.read_data(&mut data)?;
-
-        let year = packed_bcd_to_decimal(data[Register::YEAR as usize + 1]);
-        let month = packed_bcd_to_decimal(data[Register::MONTH as usize + 1] & !BitFlags::CENTURY);
-        let day = packed_bcd_to_decimal(data[Register::DOM as usize + 1]);
-        let weekday = packed_bcd_to_decimal(data[Register::DOW as usize + 1])
-            .try_into()
-            .map_err(|_| Error::InvalidInputData)?;
-        let hour = hours_from_register(data[Register::HOURS as usize + 1]);
-        let minute = packed_bcd_to_decimal(data[Register::MINUTES as usize + 1]);
-        let second = packed_bcd_to_decimal(data[Register::SECONDS as usize + 1]);
-
-        Ok(Datetime {
-            date: Date {
-                year,
-                month,
-                day,
-                weekday,
-            },
-            time: Time {
-                hour: get_h24(hour),
-                minute,
-                second: Some(second),
-            },
-        })
-    }
-
-    pub fn set_datetime(&mut self, datetime: &Datetime) -> Result<(), Error<CommE>> {
-        let (month, year) = month_year_to_registers(datetime.date.month, datetime.date.year);
-        let payload = [
-            Register::SECONDS,
-            decimal_to_packed_bcd(datetime.time.second.unwrap_or_default()),
-            decimal_to_packed_bcd(datetime.time.minute),
-            hours_to_register(Hours::H24(datetime.time.hour))?,
-            datetime.date.weekday as u8,
-            decimal_to_packed_bcd(datetime.date.day),
-            month,
-            year,
-        ];
-        self.write_data(&payload)
-    }
-    /// Write to the RTC via the I2C interface.
-    fn write_data(&mut self, payload: &[u8]) -> Result<(), Error<CommE>> {
-        self.i2c.write(DEVICE_ADDRESS, payload).map_err(Error::Comm)
-    }
-
-    /// Read the RTC via the I2C interface.
-    fn read_data(&mut self, payload: &mut [u8]) -> Result<(), Error<CommE>> {
-        let len = payload.len();
-        self.i2c
-            .write_read(DEVICE_ADDRESS, &[payload[0]], &mut payload[1..len])
-            .map_err(Error::Comm)
-    }
+    let len = payload.len();
+    i2c.write_read(device_address, &[payload[0]], &mut payload[1..len])
+        .map_err(Error::Comm)
 }
 
 /// Transform a decimal number to packed BCD format
@@ -145,6 +192,7 @@ fn packed_bcd_to_decimal(bcd: u8) -> u8 {
     (bcd >> 4) * 10 + (bcd & 0xF)
 }
 
+#[cfg(not(feature = "pcf8563"))]
 fn hours_to_register<CommE>(hours: Hours) -> Result<u8, Error<CommE>> {
     match hours {
         Hours::H24(h) if h > 23 => Err(Error::InvalidInputData),
@@ -156,6 +204,7 @@ fn hours_to_register<CommE>(hours: Hours) -> Result<u8, Error<CommE>> {
     }
 }
 
+#[cfg(not(feature = "pcf8563"))]
 fn hours_from_register(data: u8) -> Hours {
     if is_24h_format(data) {
         Hours::H24(packed_bcd_to_decimal(data & !BitFlags::H24_H12))
@@ -170,18 +219,22 @@ fn hours_from_register(data: u8) -> Hours {
     }
 }
 
+#[cfg(not(feature = "pcf8563"))]
 fn month_year_to_registers(month: u8, year: u8) -> (u8, u8) {
     (decimal_to_packed_bcd(month), decimal_to_packed_bcd(year))
 }
 
+#[cfg(not(feature = "pcf8563"))]
 fn is_24h_format(hours_data: u8) -> bool {
     hours_data & BitFlags::H24_H12 == 0
 }
 
+#[cfg(not(feature = "pcf8563"))]
 fn is_am(hours_data: u8) -> bool {
     hours_data & BitFlags::AM_PM == 0
 }
 
+#[cfg(not(feature = "pcf8563"))]
 fn get_h24(hour: Hours) -> u8 {
     match hour {
         Hours::H24(h) => h,
@@ -0,0 +1,121 @@
+//! DS1307 RTC Interface: same registers of the datetime as the DS3231,
+//! without century bit. The oscillator is halted by a bit of the seconds,
+//! set at the first power-up. No temperature, aging offset nor alarm.
+// https://www.analog.com/media/en/technical-documentation/data-sheets/ds1307.pdf
+
+use super::{
+    decimal_to_packed_bcd, get_h24, hours_from_register, hours_to_register,
+    month_year_to_registers, packed_bcd_to_decimal, read_registers, write_registers, Error, Hours,
+    Rtc,
+};
+use crate::clocks::{Date, Datetime, Time};
+use embedded_hal::blocking::i2c;
+
+struct Register;
+
+impl Register {
+    const SECONDS: u8 = 0x00;
+    const MINUTES: u8 = 0x01;
+    const HOURS: u8 = 0x02;
+    const DOW: u8 = 0x03;
+    const DOM: u8 = 0x04;
+    const MONTH: u8 = 0x05;
+    const YEAR: u8 = 0x06;
+}
+
+struct BitFlags;
+
+impl BitFlags {
+    /// Seconds register: clock halt, the oscillator is stopped
+    const CH: u8 = 0b1000_0000;
+}
+
+const DEVICE_ADDRESS: u8 = 0b110_1000;
+
+#[derive(Debug, Default)]
+pub struct Ds1307<I2C>
+where
+    I2C: i2c::Write + i2c::WriteRead,
+{
+    i2c: I2C,
+}
+
+impl<I2C, CommE> Ds1307<I2C>
+where
+    I2C: i2c::Write<Error = CommE> + i2c::WriteRead<Error = CommE>,
+{
+    /// Create a new instance of the DS1307 device.
+    pub fn init(i2c: I2C) -> Self {
+        Ds1307 { i2c }
+    }
+
+    /// Write to the RTC via the I2C interface.
+    fn write_data(&mut self, payload: &[u8]) -> Result<(), Error<CommE>> {
+        write_registers(&mut self.i2c, DEVICE_ADDRESS, payload)
+    }
+
+    /// Read the RTC via the I2C interface.
+    fn read_data(&mut self, payload: &mut [u8]) -> Result<(), Error<CommE>> {
+        read_registers(&mut self.i2c, DEVICE_ADDRESS, payload)
+    }
+}
+
+impl<I2C, CommE> Rtc for Ds1307<I2C>
+where
+    I2C: i2c::Write<Error = CommE> + i2c::WriteRead<Error = CommE>,
+{
+    type CommE = CommE;
+
+    fn datetime(&mut self) -> Result<Datetime, Error<CommE>> {
+        let mut data = [0; 8];
+        self.read_data(&mut data)?;
+
+        let year = packed_bcd_to_decimal(data[Register::YEAR as usize + 1]);
+        let month = packed_bcd_to_decimal(data[Register::MONTH as usize + 1]);
+        let day = packed_bcd_to_decimal(data[Register::DOM as usize + 1]);
+        let weekday = packed_bcd_to_decimal(data[Register::DOW as usize + 1])
+            .try_into()
+            .map_err(|_| Error::InvalidInputData)?;
+        let hour = hours_from_register(data[Register::HOURS as usize + 1]);
+        let minute = packed_bcd_to_decimal(data[Register::MINUTES as usize + 1]);
+        let second = packed_bcd_to_decimal(data[Register::SECONDS as usize + 1] & !BitFlags::CH);
+
+        Ok(Datetime {
+            date: Date {
+                year,
+                month,
+                day,
+                weekday,
+            },
+            time: Time {
+                hour: get_h24(hour),
+                minute,
+                second: Some(second),
+            },
+        })
+    }
+
+    /// Set the datetime, the clock halt bit being cleared (starting the oscillator).
+    fn set_datetime(&mut self, datetime: &Datetime) -> Result<(), Error<CommE>> {
+        let (month, year) = month_year_to_registers(datetime.date.month, datetime.date.year);
+        let payload = [
+            Register::SECONDS,
+            decimal_to_packed_bcd(datetime.time.second.unwrap_or_default()),
+            decimal_to_packed_bcd(datetime.time.minute),
+            hours_to_register(Hours::H24(datetime.time.hour))?,
+            datetime.date.weekday as u8,
+            decimal_to_packed_bcd(datetime.date.day),
+            month,
+            year,
+        ];
+        self.write_data(&payload)
+    }
+
+    /// Return true if the clock is halted, which is the case
+    /// from the first power-up until the datetime is set.
+    fn oscillator_stopped(&mut self) -> Result<bool, Error<CommE>> {
+        let mut data = [Register::SECONDS, 0];
+        self.read_data(&mut data)?;
+        Ok(data[1] & BitFlags::CH != 0)
+    }
+}
@@ -0,0 +1,252 @@
+//! DS3231 RTC Interface
+// https://www.analog.com/media/en/technical-documentation/data-sheets/ds3231.pdf
+
+use super::{
+    decimal_to_packed_bcd, get_h24, hours_from_register, hours_to_register,
+    month_year_to_registers, packed_bcd_to_decimal, read_registers, write_registers, Alarm, Error,
+    Hours, Rtc, Temperature,
+};
+use crate::clocks::{Date, Datetime, Time};
+use embedded_hal::blocking::i2c;
+
+/// Temperature conversion rate
+///
+/// This is only available on the DS3232 and DS3234 devices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TempConvRate {
+    /// Once every 64 seconds (default)
+    _64s,
+    /// Once every 128 seconds
+    _128s,
+    /// Once every 256 seconds
+    _256s,
+    /// Once every 512 seconds
+    _512s,
+}
+
+struct Register;
+
+impl Register {
+    const SECONDS: u8 = 0x00;
+    const MINUTES: u8 = 0x01;
+    const HOURS: u8 = 0x02;
+    const DOW: u8 = 0x03;
+    const DOM: u8 = 0x04;
+    const MONTH: u8 = 0x05;
+    const YEAR: u8 = 0x06;
+    const ALARM1_SECONDS: u8 = 0x07;
+    const ALARM2_MINUTES: u8 = 0x0B;
+    const CONTROL: u8 = 0x0E;
+    const STATUS: u8 = 0x0F;
+    const AGING_OFFSET: u8 = 0x10;
+    const TEMP_MSB: u8 = 0x11;
+}
+
+struct BitFlags;
+
+impl BitFlags {
+    const CENTURY: u8 = 0b1000_0000;
+    /// Alarm registers: mask of the field, ignored by the comparison
+    const ALARM_MASK: u8 = 0b1000_0000;
+    /// Control register: convert temperature
+    const CONV: u8 = 0b0010_0000;
+    /// Control register: interrupt control, INT/SQW signals the alarms
+    const INTCN: u8 = 0b0000_0100;
+    /// Control register: alarm 2 interrupt enable
+    const A2IE: u8 = 0b0000_0010;
+    /// Control register: alarm 1 interrupt enable
+    const A1IE: u8 = 0b0000_0001;
+    /// Status register: oscillator stop flag
+    const OSF: u8 = 0b1000_0000;
+    /// Status register: alarm 2 flag
+    const A2F: u8 = 0b0000_0010;
+    /// Status register: alarm 1 flag
+    const A1F: u8 = 0b0000_0001;
+    /// Status register: busy (conversion of the temperature in progress)
+    const BSY: u8 = 0b0000_0100;
+}
+
+const DEVICE_ADDRESS: u8 = 0b110_1000;
+
+#[derive(Debug, Default)]
+pub struct Ds3231<I2C>
+where
+    I2C: i2c::Write + i2c::WriteRead,
+{
+    i2c: I2C,
+}
+
+impl<I2C, CommE> Ds3231<I2C>
+where
+    I2C: i2c::Write<Error = CommE> + i2c::WriteRead<Error = CommE>,
+{
+    /// Create a new instance of the DS3231 device.
+    pub fn init(i2c: I2C) -> Self {
+        Ds3231 { i2c }
+    }
+
+    /// Write to the RTC via the I2C interface.
+    fn write_data(&mut self, payload: &[u8]) -> Result<(), Error<CommE>> {
+        write_registers(&mut self.i2c, DEVICE_ADDRESS, payload)
+    }
+
+    /// Read the RTC via the I2C interface.
+    fn read_data(&mut self, payload: &mut [u8]) -> Result<(), Error<CommE>> {
+        read_registers(&mut self.i2c, DEVICE_ADDRESS, payload)
+    }
+}
+
+impl<I2C, CommE> Rtc for Ds3231<I2C>
+where
+    I2C: i2c::Write<Error = CommE> + i2c::WriteRead<Error = CommE>,
+{
+    type CommE = CommE;
+
+    fn datetime(&mut self) -> Result<Datetime, Error<CommE>> {
+        let mut data = [0; 8];
+        self.read_data(&mut data)?;
+
+        let year = packed_bcd_to_decimal(data[Register::YEAR as usize + 1]);
+        let month = packed_bcd_to_decimal(data[Register::MONTH as usize + 1] & !BitFlags::CENTURY);
+        let day = packed_bcd_to_decimal(data[Register::DOM as usize + 1]);
+        let weekday = packed_bcd_to_decimal(data[Register::DOW as usize + 1])
+            .try_into()
+            .map_err(|_| Error::InvalidInputData)?;
+        let hour = hours_from_register(data[Register::HOURS as usize + 1]);
+        let minute = packed_bcd_to_decimal(data[Register::MINUTES as usize + 1]);
+        let second = packed_bcd_to_decimal(data[Register::SECONDS as usize + 1]);
+
+        Ok(Datetime {
+            date: Date {
+                year,
+                month,
+                day,
+                weekday,
+            },
+            time: Time {
+                hour: get_h24(hour),
+                minute,
+                second: Some(second),
+            },
+        })
+    }
+
+    /// Set the datetime, clearing the oscillator stop flag.
+    fn set_datetime(&mut self, datetime: &Datetime) -> Result<(), Error<CommE>> {
+        let (month, year) = month_year_to_registers(datetime.date.month, datetime.date.year);
+        let payload = [
+            Register::SECONDS,
+            decimal_to_packed_bcd(datetime.time.second.unwrap_or_default()),
+            decimal_to_packed_bcd(datetime.time.minute),
+            hours_to_register(Hours::H24(datetime.time.hour))?,
+            datetime.date.weekday as u8,
+            decimal_to_packed_bcd(datetime.date.day),
+            month,
+            year,
+        ];
+        self.write_data(&payload)?;
+
+        let mut data = [Register::STATUS, 0];
+        self.read_data(&mut data)?;
+        self.write_data(&[Register::STATUS, data[1] & !BitFlags::OSF])
+    }
+
+    /// Return true if the oscillator stopped since the datetime was last set
+    /// (at the first power-up, or if the battery failed): the datetime is wrong.
+    fn oscillator_stopped(&mut self) -> Result<bool, Error<CommE>> {
+        let mut data = [Register::STATUS, 0];
+        self.read_data(&mut data)?;
+        Ok(data[1] & BitFlags::OSF != 0)
+    }
+
+    /// Read the temperature of the sensor, converted every 64 seconds
+    /// or when forced (see `convert_temperature`).
+    fn temperature(&mut self) -> Result<Temperature, Error<CommE>> {
+        let mut data = [Register::TEMP_MSB, 0, 0];
+        self.read_data(&mut data)?;
+        // Two's complement over 10 bits, left-justified, in quarters of degree
+        Ok(Temperature(i16::from_be_bytes([data[1], data[2]]) >> 6))
+    }
+
+    /// Force a conversion of the temperature, unless one is already
+    /// in progress: return true if started.
+    fn convert_temperature(&mut self) -> Result<bool, Error<CommE>> {
+        // Control and status registers
+        let mut data = [Register::CONTROL, 0, 0];
+        self.read_data(&mut data)?;
+        let (control, status) = (data[1], data[2]);
+        if control & BitFlags::CONV != 0 || status & BitFlags::BSY != 0 {
+            return Ok(false);
+        }
+        self.write_data(&[Register::CONTROL, control | BitFlags::CONV])?;
+        Ok(true)
+    }
+
+    /// Read the aging offset, added to the capacitance of the crystal:
+    /// one unit slows the oscillator by about 0.1 ppm at 25°C.
+    fn aging_offset(&mut self) -> Result<i8, Error<CommE>> {
+        let mut data = [Register::AGING_OFFSET, 0];
+        self.read_data(&mut data)?;
+        Ok(data[1] as i8)
+    }
+
+    /// Write the aging offset, applied from the next conversion
+    /// of the temperature, which is forced.
+    fn set_aging_offset(&mut self, aging_offset: i8) -> Result<(), Error<CommE>> {
+        self.write_data(&[Register::AGING_OFFSET, aging_offset as u8])?;
+        self.convert_temperature()?;
+        Ok(())
+    }
+
+    /// Program an alarm to fire every day at a given time (the second
+    /// being ignored), asserting the INT/SQW output, or disable it.
+    fn set_alarm(&mut self, alarm: Alarm, time: Option<Time>) -> Result<(), Error<CommE>> {
+        if let Some(time) = time {
+            let minute = decimal_to_packed_bcd(time.minute);
+            let hour = hours_to_register(Hours::H24(time.hour))?;
+            // The day of the alarm is masked: the alarm fires every day.
+            match alarm {
+                Alarm::One => self.write_data(&[
+                    Register::ALARM1_SECONDS,
+                    0,
+                    minute,
+                    hour,
+                    BitFlags::ALARM_MASK,
+                ])?,
+                Alarm::Two => self.write_data(&[
+                    Register::ALARM2_MINUTES,
+                    minute,
+                    hour,
+                    BitFlags::ALARM_MASK,
+                ])?,
+            }
+        }
+
+        let mut data = [Register::CONTROL, 0];
+        self.read_data(&mut data)?;
+        let enable = match alarm {
+            Alarm::One => BitFlags::A1IE,
+            Alarm::Two => BitFlags::A2IE,
+        };
+        let control = if time.is_some() {
+            data[1] | BitFlags::INTCN | enable
+        } else {
+            data[1] & !enable
+        };
+        self.write_data(&[Register::CONTROL, control])
+    }
+
+    /// Read the flags of the alarms which fired, alarm 1 and alarm 2.
+    fn alarm_flags(&mut self) -> Result<(bool, bool), Error<CommE>> {
+        let mut data = [Register::STATUS, 0];
+        self.read_data(&mut data)?;
+        Ok((data[1] & BitFlags::A1F != 0, data[1] & BitFlags::A2F != 0))
+    }
+
+    /// Clear the flags of both alarms, releasing the INT/SQW output.
+    fn clear_alarm_flags(&mut self) -> Result<(), Error<CommE>> {
+        let mut data = [Register::STATUS, 0];
+        self.read_data(&mut data)?;
+        self.write_data(&[Register::STATUS, data[1] & !(BitFlags::A1F | BitFlags::A2F)])
+    }
+}
@@ -0,0 +1,206 @@
+//! PCF8563 RTC Interface: 24-hour format only, the weekday from 0 (Sunday)
+//! to 6, unused bits to be masked, and a single alarm, on hours and minutes.
+//! The oscillator is monitored by the voltage-low bit of the seconds.
+//! No temperature nor aging offset.
+// https://www.nxp.com/docs/en/data-sheet/PCF8563.pdf
+
+use super::{
+    decimal_to_packed_bcd, packed_bcd_to_decimal, read_registers, write_registers, Alarm, Error,
+    Rtc,
+};
+use crate::clocks::{Date, Datetime, Time};
+use embedded_hal::blocking::i2c;
+
+struct Register;
+
+impl Register {
+    const CONTROL_2: u8 = 0x01;
+    const SECONDS: u8 = 0x02;
+    const MINUTES: u8 = 0x03;
+    const HOURS: u8 = 0x04;
+    const DAYS: u8 = 0x05;
+    const WEEKDAYS: u8 = 0x06;
+    const MONTHS: u8 = 0x07;
+    const YEARS: u8 = 0x08;
+    const MINUTE_ALARM: u8 = 0x09;
+}
+
+struct BitFlags;
+
+impl BitFlags {
+    /// Seconds register: voltage low, the integrity of the clock is not guaranteed
+    const VL: u8 = 0b1000_0000;
+    /// Alarm registers: alarm disabled on the field
+    const AE: u8 = 0b1000_0000;
+    /// Control register 2: bits to be written as zero
+    const CONTROL_2_UNUSED: u8 = 0b1110_0000;
+    /// Control register 2: alarm flag (written as one, left unchanged)
+    const AF: u8 = 0b0000_1000;
+    /// Control register 2: timer flag (written as one, left unchanged)
+    const TF: u8 = 0b0000_0100;
+    /// Control register 2: alarm interrupt enable
+    const AIE: u8 = 0b0000_0010;
+}
+
+/// Masks of the bits of the datetime registers
+struct Masks;
+
+impl Masks {
+    const SECONDS: u8 = 0x7f;
+    const MINUTES: u8 = 0x7f;
+    const HOURS: u8 = 0x3f;
+    const DAYS: u8 = 0x3f;
+    const WEEKDAYS: u8 = 0x07;
+    /// The century bit is ignored
+    const MONTHS: u8 = 0x1f;
+}
+
+const DEVICE_ADDRESS: u8 = 0b101_0001;
+
+#[derive(Debug, Default)]
+pub struct Pcf8563<I2C>
+where
+    I2C: i2c::Write + i2c::WriteRead,
+{
+    i2c: I2C,
+}
+
+impl<I2C, CommE> Pcf8563<I2C>
+where
+    I2C: i2c::Write<Error = CommE> + i2c::WriteRead<Error = CommE>,
+{
+    /// Create a new instance of the PCF8563 device.
+    pub fn init(i2c: I2C) -> Self {
+        Pcf8563 { i2c }
+    }
+
+    /// Write the control register 2, leaving the flags unchanged
+    /// unless cleared.
+    fn write_control_2(&mut self, control: u8, cleared_flags: u8) -> Result<(), Error<CommE>> {
+        let control =
+            (control | BitFlags::AF | BitFlags::TF) & !(BitFlags::CONTROL_2_UNUSED | cleared_flags);
+        self.write_data(&[Register::CONTROL_2, control])
+    }
+
+    /// Write to the RTC via the I2C interface.
+    fn write_data(&mut self, payload: &[u8]) -> Result<(), Error<CommE>> {
+        write_registers(&mut self.i2c, DEVICE_ADDRESS, payload)
+    }
+
+    /// Read the RTC via the I2C interface.
+    fn read_data(&mut self, payload: &mut [u8]) -> Result<(), Error<CommE>> {
+        read_registers(&mut self.i2c, DEVICE_ADDRESS, payload)
+    }
+}
+
+impl<I2C, CommE> Rtc for Pcf8563<I2C>
+where
+    I2C: i2c::Write<Error = CommE> + i2c::WriteRead<Error = CommE>,
+{
+    type CommE = CommE;
+
+    fn datetime(&mut self) -> Result<Datetime, Error<CommE>> {
+        let mut data = [Register::SECONDS, 0, 0, 0, 0, 0, 0, 0];
+        self.read_data(&mut data)?;
+        let register =
+            |address: u8, mask: u8| data[(address - Register::SECONDS) as usize + 1] & mask;
+
+        let year = packed_bcd_to_decimal(register(Register::YEARS, 0xff));
+        let month = packed_bcd_to_decimal(register(Register::MONTHS, Masks::MONTHS));
+        let day = packed_bcd_to_decimal(register(Register::DAYS, Masks::DAYS));
+        let weekday = match register(Register::WEEKDAYS, Masks::WEEKDAYS) {
+            0 => 7,
+            weekday => weekday,
+        }
+        .try_into()
+        .map_err(|_| Error::InvalidInputData)?;
+        let hour = packed_bcd_to_decimal(register(Register::HOURS, Masks::HOURS));
+        let minute = packed_bcd_to_decimal(register(Register::MINUTES, Masks::MINUTES));
+        let second = packed_bcd_to_decimal(register(Register::SECONDS, Masks::SECONDS));
+
+        Ok(Datetime {
+            date: Date {
+                year,
+                month,
+                day,
+                weekday,
+            },
+            time: Time {
+                hour,
+                minute,
+                second: Some(second),
+            },
+        })
+    }
+
+    /// Set the datetime, the voltage-low bit being cleared.
+    fn set_datetime(&mut self, datetime: &Datetime) -> Result<(), Error<CommE>> {
+        if datetime.time.hour > 23 {
+            return Err(Error::InvalidInputData);
+        }
+        let payload = [
+            Register::SECONDS,
+            decimal_to_packed_bcd(datetime.time.second.unwrap_or_default()),
+            decimal_to_packed_bcd(datetime.time.minute),
+            decimal_to_packed_bcd(datetime.time.hour),
+            decimal_to_packed_bcd(datetime.date.day),
+            datetime.date.weekday as u8 % 7,
+            decimal_to_packed_bcd(datetime.date.month),
+            decimal_to_packed_bcd(datetime.date.year),
+        ];
+        self.write_data(&payload)
+    }
+
+    /// Return true if the voltage dropped below the minimum (at the first
+    /// power-up, or if the battery failed) since the datetime was last set.
+    fn oscillator_stopped(&mut self) -> Result<bool, Error<CommE>> {
+        let mut data = [Register::SECONDS, 0];
+        self.read_data(&mut data)?;
+        Ok(data[1] & BitFlags::VL != 0)
+    }
+
+    /// Program the alarm, which is the only one (alarm 1), to fire every day
+    /// at a given time, asserting the INT output, or disable it.
+    fn set_alarm(&mut self, alarm: Alarm, time: Option<Time>) -> Result<(), Error<CommE>> {
+        if alarm != Alarm::One {
+            return Err(Error::Unsupported);
+        }
+        if let Some(time) = time {
+            if time.hour > 23 {
+                return Err(Error::InvalidInputData);
+            }
+            // The day and the weekday of the alarm are disabled:
+            // the alarm fires every day.
+            self.write_data(&[
+                Register::MINUTE_ALARM,
+                decimal_to_packed_bcd(time.minute),
+                decimal_to_packed_bcd(time.hour),
+                BitFlags::AE,
+                BitFlags::AE,
+            ])?;
+        }
+
+        let mut data = [Register::CONTROL_2, 0];
+        self.read_data(&mut data)?;
+        let control = if time.is_some() {
+            data[1] | BitFlags::AIE
+        } else {
+            data[1] & !BitFlags::AIE
+        };
+        self.write_control_2(control, 0)
+    }
+
+    /// Read the flag of the alarm (alarm 1), the alarm 2 being absent.
+    fn alarm_flags(&mut self) -> Result<(bool, bool), Error<CommE>> {
+        let mut data = [Register::CONTROL_2, 0];
+        self.read_data(&mut data)?;
+        Ok((data[1] & BitFlags::AF != 0, false))
+    }
+
+    /// Clear the flag of the alarm, releasing the INT output.
+    fn clear_alarm_flags(&mut self) -> Result<(), Error<CommE>> {
+        let mut data = [Register::CONTROL_2, 0];
+        self.read_data(&mut data)?;
+        self.write_control_2(data[1], BitFlags::AF)
+    }
+}
@@ -1,10 +1,13 @@
 //! Timer based on the internal clock, moderately accurate.
 use crate::{Dcf77Input, DCF77_LOGICAL_LEVEL_HIGH};
+pub use alarm_clock_decoder::timer::{Timer, POLLED_SAMPLES_PERIOD_MS};
 use arduino_hal::port::{
     mode::{Input, Io, PullUp},
     Pin, PinOps,
 };
 use core::cell::RefCell;
+#[cfg(feature = "dcf77-int0")]
+use ringbuffer::{ConstGenericRingBuffer, RingBuffer};
 
 /// Prescaler of the internal timer (see the doc of the microprocessor)
 const PRESCALER: u16 = 64;
@@ -24,66 +27,14 @@ const TIMER_COUNTS: u8 = 250;
 /// ╚═══════════╩══════════════╩═══════════════════╝
 ///
 pub const MILLIS_INCREMENT: u16 = (((PRESCALER as u32) * (TIMER_COUNTS as u32)) / 16000u32) as u16;
-/// Downsampling factor for the polling of the DCF77 input
-pub const POLLED_SAMPLES_PERIOD_MS: u16 = 25;
-/// The downsampling factor is based on the modulo of the timer.
-/// The rolling of the timer must occur at the biggest multiple of the POLLED_SAMPLES_PERIOD_MS
-/// without overflowing the capacity of the integer type.
-const TIMER_MAX: u16 = (u16::MAX / POLLED_SAMPLES_PERIOD_MS) * POLLED_SAMPLES_PERIOD_MS;
+/// Number of edges of the DCF77 input kept until they are processed
+#[cfg(feature = "dcf77-int0")]
+const EDGES_CAPACITY: usize = 8;
 
 /// Global timer object
 static TIMER: avr_device::interrupt::Mutex<RefCell<Option<TimerPolling<Dcf77Input>>>> =
     avr_device::interrupt::Mutex::new(RefCell::new(None));
 
-/// Timer structure, new-type pattern
-#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Timer(pub u16);
-
-impl ufmt::uDisplay for Timer {
-    /// Formatter for the serial output.
-    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
-    where
-        W: ufmt::uWrite + ?Sized,
-    {
-        self.0.fmt(f)
-    }
-}
-
-impl Timer {
-    /// Increment the timer, wrapping at TIMER_MAX.
-    fn increment(&mut self) {
-        self.0 += MILLIS_INCREMENT;
-        if self.0 > TIMER_MAX {
-            self.0 = 1;
-        }
-    }
-}
-
-impl core::ops::Sub for Timer {
-    type Output = Timer;
-
-    /// Difference of timer, taking into account wrapping.
-    /// The result is meaningful only if the time between both arguments
-    /// are less than TIMER_MAX apart.
-    fn sub(self, rhs: Self) -> Self::Output {
-        Timer(if self.0 >= rhs.0 {
-            self.0 - rhs.0
-        } else {
-            let rhs = TIMER_MAX - rhs.0;
-            self.0 + rhs
-        })
-    }
-}
-
-impl core::ops::Rem<u16> for Timer {
-    type Output = u16;
-
-    /// Modulo operation on the timer.
-    fn rem(self, rhs: u16) -> Self::Output {
-        self.0 % rhs
-    }
-}
-
 /// Timer structure holding polled values of the DCF77 input
 struct TimerPolling<PIN> {
     /// Timer per se
@@ -91,10 +42,21 @@ struct TimerPolling<PIN> {
     /// DCF77 pin input
     pin: Pin<Input<PullUp>, PIN>,
     /// Latest polled value, set during the interrupt, with the timer value of its setting
+    #[cfg(not(feature = "dcf77-int0"))]
     polled_value: Option<(Timer, bool)>,
     /// Counter for the downsampling of DCF77,
     /// incremented for high (logical) levels, decremented for low (logical) levels
+    #[cfg(not(feature = "dcf77-int0"))]
     downsampling_counter_dcf77: i8,
+    /// Edges of DCF77 not yet processed, set during the external interrupt,
+    /// with the timer value and the logical level after the edge
+    #[cfg(feature = "dcf77-int0")]
+    edges: ConstGenericRingBuffer<(Timer, bool), EDGES_CAPACITY>,
+    /// Correction of the rate of the timer, in 1/65536 ms per ms
+    correction: i16,
+    /// Accumulator of the correction: a millisecond is added (or skipped)
+    /// each time it overflows
+    correction_accumulator: u16,
 }
 
 impl<PIN: PinOps> TimerPolling<PIN> {
@@ -103,37 +65,82 @@ impl<PIN: PinOps> TimerPolling<PIN> {
         Self {
             timer: Timer::default(),
             pin,
+            #[cfg(not(feature = "dcf77-int0"))]
             polled_value: None,
+            #[cfg(not(feature = "dcf77-int0"))]
             downsampling_counter_dcf77: 0,
+            #[cfg(feature = "dcf77-int0")]
+            edges: ConstGenericRingBuffer::new(),
+            correction: 0,
+            correction_accumulator: 0,
+        }
+    }
+
+    /// Number of increments of the timer for the current interrupt:
+    /// usually one, but two or none when the correction accumulated
+    /// to a full millisecond.
+    fn corrected_increments(&mut self) -> u8 {
+        let (accumulator, overflow) = self
+            .correction_accumulator
+            .overflowing_add(self.correction.unsigned_abs());
+        self.correction_accumulator = accumulator;
+        match (overflow, self.correction > 0) {
+            (false, _) => 1,
+            (true, true) => 2,
+            (true, false) => 0,
         }
     }
 }
 
 /// Timer interrupt function. Borrow the global timer, increment the counter,
 /// poll DFC77 input and optionaly publish (setting Some) a downsampled value
-/// of DCF77.
+/// of DCF77 (unless its edges are timestamped by the external interrupt).
 #[avr_device::interrupt(atmega328p)]
 fn TIMER0_COMPA() {
     avr_device::interrupt::free(|cs| {
         if let Some(timer) = TIMER.borrow(cs).borrow_mut().as_mut() {
-            timer.timer.increment();
-
+            #[cfg(not(feature = "dcf77-int0"))]
             if timer.pin.is_high() == DCF77_LOGICAL_LEVEL_HIGH {
                 timer.downsampling_counter_dcf77 += 1;
             } else {
                 timer.downsampling_counter_dcf77 -= 1;
             }
 
-            if timer.timer % POLLED_SAMPLES_PERIOD_MS == 0 {
-                timer.polled_value = Some((timer.timer, timer.downsampling_counter_dcf77 > 0));
-                timer.downsampling_counter_dcf77 = 0;
+            // Check every increment, so that no multiple of the period
+            // is skipped when the timer is incremented twice.
+            for _ in 0..timer.corrected_increments() {
+                timer.timer.increment(MILLIS_INCREMENT);
+                #[cfg(not(feature = "dcf77-int0"))]
+                if timer.timer % POLLED_SAMPLES_PERIOD_MS == 0 {
+                    timer.polled_value = Some((timer.timer, timer.downsampling_counter_dcf77 > 0));
+                    timer.downsampling_counter_dcf77 = 0;
+                }
             }
         }
     })
 }
 
-/// Initialize the registrers for the hardware timer.
-pub fn init<MODE: Io>(tc0: arduino_hal::pac::TC0, pin: Pin<MODE, Dcf77Input>) {
+/// External interrupt function, on both edges of the DCF77 input:
+/// timestamp the edge with the timer, along with the new logical level.
+#[cfg(feature = "dcf77-int0")]
+#[avr_device::interrupt(atmega328p)]
+fn INT0() {
+    avr_device::interrupt::free(|cs| {
+        if let Some(timer) = TIMER.borrow(cs).borrow_mut().as_mut() {
+            let level = timer.pin.is_high() == DCF77_LOGICAL_LEVEL_HIGH;
+            let now = timer.timer;
+            timer.edges.push((now, level));
+        }
+    })
+}
+
+/// Initialize the registrers for the hardware timer
+/// (and for the external interrupt on the DCF77 input, if enabled).
+pub fn init<MODE: Io>(
+    tc0: arduino_hal::pac::TC0,
+    #[cfg(feature = "dcf77-int0")] exint: arduino_hal::pac::EXINT,
+    pin: Pin<MODE, Dcf77Input>,
+) {
     // Configure the timer for the above interval (in CTC mode)
     // and enable its interrupt.
     tc0.tccr0a.write(|w| w.wgm0().ctc());
@@ -147,6 +154,13 @@ pub fn init<MODE: Io>(tc0: arduino_hal::pac::TC0, pin: Pin<MODE, Dcf77Input>) {
     });
     tc0.timsk0.write(|w| w.ocie0a().set_bit());
 
+    // Raise the external interrupt on any change of the DCF77 input (INT0).
+    #[cfg(feature = "dcf77-int0")]
+    {
+        exint.eicra.modify(|_, w| w.isc0().bits(0x01));
+        exint.eimsk.modify(|_, w| w.int0().set_bit());
+    }
+
     // Reset the global millisecond counter
     avr_device::interrupt::free(|cs| {
         let mut timer = TIMER.borrow(cs).borrow_mut();
@@ -159,8 +173,19 @@ pub fn get_timer() -> Option<Timer> {
     avr_device::interrupt::free(|cs| TIMER.borrow(cs).borrow().as_ref().map(|timer| timer.timer))
 }
 
+/// Set the correction of the rate of the timer, in 1/65536 ms per ms:
+/// a positive correction speeds the timer up.
+pub fn set_correction(correction: i16) {
+    avr_device::interrupt::free(|cs| {
+        if let Some(timer) = TIMER.borrow(cs).borrow_mut().as_mut() {
+            timer.correction = correction;
+        }
+    })
+}
+
 /// Get the polled value of DCF77, if any, with the time when it was set
 /// to discrimate between an old value already processed and a new one.
+#[cfg(not(feature = "dcf77-int0"))]
 pub fn get_polled_values() -> Option<(Timer, bool)> {
     avr_device::interrupt::free(|cs| {
         TIMER
@@ -170,3 +195,16 @@ pub fn get_polled_values() -> Option<(Timer, bool)> {
             .and_then(|timer| timer.polled_value)
     })
 }
+
+/// Get the oldest edge of DCF77 not yet processed, if any,
+/// with the time when it occured and the logical level after it.
+#[cfg(feature = "dcf77-int0")]
+pub fn get_edge() -> Option<(Timer, bool)> {
+    avr_device::interrupt::free(|cs| {
+        TIMER
+            .borrow(cs)
+            .borrow_mut()
+            .as_mut()
+            .and_then(|timer| timer.edges.dequeue())
+    })
+}
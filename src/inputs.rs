@@ -1,5 +1,5 @@
 //! Inputs, either of the environment or of the user
-use crate::{ButtonInput, LuminosityInput, ProximityInput};
+use crate::{ButtonInput, LuminosityInput, ProximityInput, RtcAlarmInput};
 use arduino_hal::port::{
     mode::{Input, Io, PullUp},
     Pin, PinOps,
@@ -13,6 +13,8 @@ pub struct Inputs {
     pub luminosity: BoolInput<LuminosityInput>,
     /// Infrared proximity/motion sensor
     pub proximity: BoolInput<ProximityInput>,
+    /// INT/SQW output of the RTC, asserted by its alarms
+    pub rtc_alarm: BoolInput<RtcAlarmInput>,
 }
 
 impl Inputs {
@@ -25,11 +27,14 @@ impl Inputs {
         luminosity_elec_level_to_logical_level: bool,
         proximity_pin: Pin<impl Io, ProximityInput>,
         proximity_elec_level_to_logical_level: bool,
+        rtc_alarm_pin: Pin<impl Io, RtcAlarmInput>,
+        rtc_alarm_elec_level_to_logical_level: bool,
     ) -> Self {
         Self {
             button: BoolInput::init(button_pin, button_elec_level_to_logical_level),
             luminosity: BoolInput::init(luminosity_pin, luminosity_elec_level_to_logical_level),
             proximity: BoolInput::init(proximity_pin, proximity_elec_level_to_logical_level),
+            rtc_alarm: BoolInput::init(rtc_alarm_pin, rtc_alarm_elec_level_to_logical_level),
         }
     }
 
@@ -38,6 +43,7 @@ impl Inputs {
         self.button.update();
         self.luminosity.update();
         self.proximity.update();
+        self.rtc_alarm.update();
     }
 }
 
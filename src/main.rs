@@ -33,23 +33,39 @@ Arduino: https://docs.arduino.cc/resources/datasheets/A000066-datasheet.pdf
 type Dcf77Input = arduino_hal::hal::port::PD2; // d2
 type LuminosityInput = arduino_hal::hal::port::PD3; // d3
 type ButtonInput = arduino_hal::hal::port::PD4; // d4
+#[cfg(feature = "dcf77-pon")]
+type Dcf77PowerOutput = arduino_hal::hal::port::PD5; // d5
 type ProximityInput = arduino_hal::hal::port::PD6; // d6
 type BuzzerOutput = arduino_hal::hal::port::PD7; // d7
 type LedStripDataOutput = arduino_hal::hal::port::PB0; // d8
+#[cfg(feature = "dcf77-tx")]
+type Dcf77TransmitterOutput = arduino_hal::hal::port::PB1; // d9
 type DisplaySpiCsOutput = arduino_hal::hal::port::PB2; // d10
 type DisplaySpiMosiOutput = arduino_hal::hal::port::PB3; // d11
+type RtcAlarmInput = arduino_hal::hal::port::PB4; // d12
 type DisplaySpiClkOutput = arduino_hal::hal::port::PB5; // d13
 
 /// Mapping between the electric levels (+3.3V or +5V) and the logical level of the DCF77 receiver
 const DCF77_LOGICAL_LEVEL_HIGH: bool = false;
+/// Mapping between the electric levels (+3.3V or +5V) and the logical level (powered)
+/// of the PON input of the DCF77 receiver
+#[cfg(feature = "dcf77-pon")]
+const DCF77_PON_LOGICAL_LEVEL_HIGH: bool = false;
 /// Mapping between the electric levels (+3.3V or +5V) and the logical level of the luminosity sensor
 const LUMINOSITY_LOGICAL_LEVEL_HIGH: bool = false;
 /// Mapping between the electric levels (+3.3V or +5V) and the logical level of the proximity sensor
 const PROXIMITY_LOGICAL_LEVEL_HIGH: bool = false;
 /// Mapping between the electric levels (+3.3V or +5V) and the logical level of the button
 const BUTTON_LOGICAL_LEVEL_HIGH: bool = false;
+/// Mapping between the electric levels (+3.3V or +5V) and the logical level (asserted)
+/// of the INT/SQW output of the RTC, open drain and active low
+const RTC_ALARM_LOGICAL_LEVEL_HIGH: bool = false;
 /// Mapping between the electric levels (+3.3V or +5V) and the logical level of the buzzer
 const BUZZER_LOGICAL_LEVEL_HIGH: bool = true;
+/// Mapping between the electric levels (+3.3V or +5V) and the logical level (reduced carrier)
+/// of the input of the DCF77 transmitter
+#[cfg(feature = "dcf77-tx")]
+const DCF77_TRANSMITTER_LOGICAL_LEVEL_HIGH: bool = false;
 /// Number of individual leds on the strip
 const LED_STRIP_COUNT: usize = 180;
 /// Value of the brightness of the simulated day
@@ -69,21 +85,42 @@ const ALARM_WEEKEND_SUNRISE: Time = Time {
     minute: 10,
     second: None,
 };
+/// Display the quality of the DCF77 reception as a bar next to the time
+const DISPLAY_DCF77_QUALITY: bool = true;
+/// Display the temperature of the RTC next to the time
+const DISPLAY_TEMPERATURE: bool = false;
 /// Ack the alarm (if not already done manually) after that time
 const ALARM_AUTO_ACK_MIN: u8 = 5;
+/// Duration during which the offset to UTC given by DCF77 is trusted
+/// over the rule of summer time
+const DCF77_UTC_OFFSET_VALIDITY_MINUTES: i16 = 60;
+/// Number of minutes without any DCF77 frame before power-cycling the receiver
+#[cfg(feature = "dcf77-pon")]
+const DCF77_POWER_CYCLE_TIMEOUT_MINUTES: Option<u16> = Some(30);
+/// Window of the day (local time) when the DCF77 receiver is powered,
+/// if it is not always powered: beginning and end, for instance at night
+#[cfg(feature = "dcf77-pon")]
+const DCF77_POWER_WINDOW: Option<(Time, Time)> = None;
+/// Number of DCF77 updates of the RTC kept in the history
+const DCF77_HISTORY_LENGTH: usize = 8;
+/// Number of measures of the drift of the RTC kept in the history
+const RTC_DRIFT_HISTORY_LENGTH: usize = 8;
 
 use crate::{
-    clocks::{Clock, PhaseOfDay, Time},
+    clocks::{
+        dcf77::{CaptureEncoder, SUMMER_TIME_RULE},
+        rtc::{Rtc, SelectedRtc},
+        Clock, ClockDatetime, PhaseOfDay, Time,
+    },
     inputs::Inputs,
     outputs::{Color, DisplayIntensity, Outputs},
-    serial_commands::{Command, SerialBuffer, SunriseSelection, USART_MUTEX},
+    serial_commands::{Command, Dcf77DebugMode, SerialBuffer, SunriseSelection, USART_MUTEX},
 };
 use arduino_hal::hal::wdt;
 use core::{
     panic::PanicInfo,
     sync::atomic::{self, Ordering},
 };
-use embedded_hal::blocking::i2c;
 use ufmt::uWrite;
 
 mod clocks;
@@ -93,12 +130,9 @@ mod serial_commands;
 
 /// The main state of the whole program, updated at every loop,
 /// holding the memory.
-struct MainState<I2C, const WRITE_BUFFER_SIZE: usize, const READ_BUFFER_SIZE: usize>
-where
-    I2C: i2c::Write + i2c::WriteRead,
-{
+struct MainState<RTC: Rtc, const WRITE_BUFFER_SIZE: usize, const READ_BUFFER_SIZE: usize> {
     /// Clocks and alarms
-    clocks: Clock<I2C>,
+    clocks: Clock<RTC>,
     /// Inputs, either from the environment (light, etc.) or
     /// from the user (proximity, etc.)
     inputs: Inputs,
@@ -109,12 +143,14 @@ where
     /// If set to Some (by a command), the LED strip will display this color,
     /// overwriting the nominal one
     forced_led_color: Option<Color>,
-    /// Debug dcf77 (print bits)
-    debug_dcf77: bool,
+    /// Debug dcf77 (print bits and/or errors)
+    debug_dcf77: Dcf77DebugMode,
+    /// If set to Some (by a command), stream the raw polled values of dcf77
+    capture_dcf77: Option<CaptureEncoder>,
 }
 
 impl<const WRITE_BUFFER_SIZE: usize, const READ_BUFFER_SIZE: usize>
-    MainState<arduino_hal::I2c, WRITE_BUFFER_SIZE, READ_BUFFER_SIZE>
+    MainState<SelectedRtc<arduino_hal::I2c>, WRITE_BUFFER_SIZE, READ_BUFFER_SIZE>
 {
     /// Run all the tasks needed to update the state/inputs/outputs.
     fn run(&mut self) {
@@ -132,17 +168,49 @@ impl<const WRITE_BUFFER_SIZE: usize, const READ_BUFFER_SIZE: usize>
 
     /// Update all the outputs for the user.
     fn update_outputs(&mut self) {
-        if self.debug_dcf77 {
+        if matches!(
+            self.debug_dcf77,
+            Dcf77DebugMode::Bits | Dcf77DebugMode::Full
+        ) {
             if let Some(bit) = self.clocks.last_dcf77_bit {
                 let bit = match bit {
                     clocks::dcf77::Dcf77SignalVariant::High => '#',
                     clocks::dcf77::Dcf77SignalVariant::Low => '_',
                     clocks::dcf77::Dcf77SignalVariant::MinuteEnd => '\n',
+                    clocks::dcf77::Dcf77SignalVariant::Marker => 'M',
+                    clocks::dcf77::Dcf77SignalVariant::Pair(a, b) => {
+                        (b'0' + 2 * a as u8 + b as u8) as char
+                    }
+                    clocks::dcf77::Dcf77SignalVariant::Unknown => '?',
                 };
                 self.serial_buffer.write_char(bit).ok();
             }
         }
+        if let (Some(capture), Some((timer, value))) =
+            (self.capture_dcf77.as_mut(), self.clocks.last_dcf77_sample)
+        {
+            if let Some(line) = capture.push(timer, value) {
+                ufmt::uwriteln!(&mut self.serial_buffer, "{}", line).ok();
+            }
+        }
+        if let Some(error) = self.clocks.last_dcf77_error {
+            match self.debug_dcf77 {
+                Dcf77DebugMode::Errors => {
+                    ufmt::uwriteln!(&mut self.serial_buffer, "{}", error).ok();
+                }
+                Dcf77DebugMode::Full => {
+                    ufmt::uwrite!(&mut self.serial_buffer, "[{}]", error).ok();
+                }
+                Dcf77DebugMode::Off | Dcf77DebugMode::Bits => (),
+            }
+        }
         self.serial_buffer.flush();
+        #[cfg(feature = "dcf77-tx")]
+        self.outputs.dcf77_transmitter.update(
+            self.clocks.datetime.trusted(),
+            self.clocks.utc_offset,
+            &self.clocks.summer_time_rule,
+        );
         self.outputs.render();
     }
 
@@ -159,6 +227,8 @@ impl<const WRITE_BUFFER_SIZE: usize, const READ_BUFFER_SIZE: usize>
 
     /// Ack the alarm, if the conditions are met
     fn process_alarm(&mut self) {
+        // The alarms of the RTC trigger the sunrise even if the schedule was missed
+        self.clocks.process_rtc_alarm(self.inputs.rtc_alarm.value);
         if let PhaseOfDay::SunRise {
             elapsed_since_sunrise,
         } = self.clocks.phase_of_day
@@ -179,14 +249,32 @@ impl<const WRITE_BUFFER_SIZE: usize, const READ_BUFFER_SIZE: usize>
         let second = self
             .clocks
             .datetime
+            .unchecked()
             .and_then(|d| d.time.second)
             .unwrap_or(0xc0);
         let quarters_since_last_rtc_update = self.clocks.quarters_since_last_rtc_update();
+        // Bar of 7 pixels, filled from the bottom
+        let dcf77_quality = if DISPLAY_DCF77_QUALITY {
+            let rows = self.clocks.dcf77_metrics().quality() as u16 * 7 / 100;
+            0x7f & !(0x7f >> rows)
+        } else {
+            0
+        };
 
-        self.outputs.display.write_time(self.clocks.datetime);
+        if DISPLAY_TEMPERATURE {
+            self.outputs
+                .display
+                .write_time_and_temperature(self.clocks.datetime, self.clocks.temperature);
+        } else {
+            self.outputs.display.write_time(self.clocks.datetime);
+        }
         self.outputs.display.set_at(
             29,
-            &[second, 0, quarters_since_last_rtc_update.unwrap_or(u8::MAX)],
+            &[
+                second,
+                dcf77_quality,
+                quarters_since_last_rtc_update.unwrap_or(u8::MAX),
+            ],
         );
 
         // Compute the intensity of the display,
@@ -251,6 +339,70 @@ impl<const WRITE_BUFFER_SIZE: usize, const READ_BUFFER_SIZE: usize>
         }
     }
 
+    /// Print the metrics of the DCF77 reception on the serial port.
+    /// The output is longer than the serial buffer: flush it along the way.
+    fn print_dcf77_metrics(&mut self) {
+        let metrics = *self.clocks.dcf77_metrics();
+        ufmt::uwriteln!(
+            &mut self.serial_buffer,
+            "quality {} frames {}/{}",
+            metrics.quality(),
+            metrics.frames_decoded,
+            metrics.frames_attempted
+        )
+        .ok();
+        ufmt::uwriteln!(
+            &mut self.serial_buffer,
+            "peak {} max {} threshold {}",
+            metrics.last_peak_height,
+            metrics.max_peak_height,
+            metrics.threshold_margin
+        )
+        .ok();
+        self.serial_buffer.flush();
+        ufmt::uwriteln!(
+            &mut self.serial_buffer,
+            "lock {} {}",
+            metrics.lock_confidence,
+            if metrics.locked {
+                "locked"
+            } else {
+                "searching"
+            }
+        )
+        .ok();
+        self.serial_buffer.flush();
+        ufmt::uwrite!(
+            &mut self.serial_buffer,
+            "timer {} ppm",
+            metrics.timer_correction_ppm()
+        )
+        .ok();
+        match metrics.pll_error {
+            Some(error) => ufmt::uwriteln!(&mut self.serial_buffer, " error {} ms", error).ok(),
+            None => ufmt::uwriteln!(&mut self.serial_buffer, " error -").ok(),
+        };
+        self.serial_buffer.flush();
+        let [missed, close, far, bad, width] = metrics.workflow_errors;
+        ufmt::uwriteln!(
+            &mut self.serial_buffer,
+            "missed {} close {} far {} bad {} width {}",
+            missed,
+            close,
+            far,
+            bad,
+            width
+        )
+        .ok();
+        self.serial_buffer.flush();
+        ufmt::uwrite!(&mut self.serial_buffer, "proto").ok();
+        for count in metrics.protocol_errors {
+            ufmt::uwrite!(&mut self.serial_buffer, " {}", count).ok();
+            self.serial_buffer.flush();
+        }
+        ufmt::uwriteln!(&mut self.serial_buffer, "").ok();
+    }
+
     /// Process commands received on serial input, if any
     fn process_command(&mut self) {
         loop {
@@ -259,10 +411,25 @@ impl<const WRITE_BUFFER_SIZE: usize, const READ_BUFFER_SIZE: usize>
                     break;
                 }
                 Ok(Some(Command::QueryDatetime)) => match self.clocks.datetime {
-                    Some(datetime) => {
-                        ufmt::uwriteln!(&mut self.serial_buffer, "{}", datetime).ok();
+                    ClockDatetime::Trusted(datetime) => {
+                        ufmt::uwriteln!(
+                            &mut self.serial_buffer,
+                            "{}{}",
+                            datetime,
+                            self.clocks.utc_offset
+                        )
+                        .ok();
                     }
-                    None => {
+                    ClockDatetime::Untrusted(datetime) => {
+                        ufmt::uwriteln!(
+                            &mut self.serial_buffer,
+                            "{}{} untrusted",
+                            datetime,
+                            self.clocks.utc_offset
+                        )
+                        .ok();
+                    }
+                    ClockDatetime::Unavailable => {
                         ufmt::uwriteln!(&mut self.serial_buffer, "None").ok();
                     }
                 },
@@ -274,6 +441,81 @@ impl<const WRITE_BUFFER_SIZE: usize, const READ_BUFFER_SIZE: usize>
                         ufmt::uwriteln!(&mut self.serial_buffer, "None").ok();
                     }
                 },
+                Ok(Some(Command::QueryRejectedDcf77Frames)) => {
+                    ufmt::uwriteln!(
+                        &mut self.serial_buffer,
+                        "{}",
+                        self.clocks.dcf77_rejected_frames()
+                    )
+                    .ok();
+                }
+                Ok(Some(Command::QueryDcf77Quality)) => {
+                    self.print_dcf77_metrics();
+                }
+                Ok(Some(Command::QueryDcf77History)) => {
+                    for update in self.clocks.dcf77_history().iter() {
+                        ufmt::uwriteln!(&mut self.serial_buffer, "{}", update).ok();
+                        self.serial_buffer.flush();
+                    }
+                }
+                Ok(Some(Command::QueryRtcDrift)) => {
+                    for measure in self.clocks.drift().iter() {
+                        ufmt::uwriteln!(&mut self.serial_buffer, "{}", measure).ok();
+                        self.serial_buffer.flush();
+                    }
+                }
+                Ok(Some(Command::QueryReceptionHistogram)) => {
+                    for (hour, counts) in self.clocks.reception().hours().iter().enumerate() {
+                        ufmt::uwriteln!(
+                            &mut self.serial_buffer,
+                            "{} {} {}",
+                            hour,
+                            counts.successes,
+                            counts.failures
+                        )
+                        .ok();
+                        self.serial_buffer.flush();
+                    }
+                }
+                #[cfg(feature = "dcf77-pon")]
+                Ok(Some(Command::QueryDcf77Power)) => {
+                    let state = if self.clocks.is_dcf77_receiver_on() {
+                        "on"
+                    } else {
+                        "off"
+                    };
+                    ufmt::uwrite!(&mut self.serial_buffer, "{} timeout ", state).ok();
+                    match self.clocks.dcf77_power_timeout() {
+                        Some(minutes) => ufmt::uwrite!(&mut self.serial_buffer, "{}", minutes),
+                        None => ufmt::uwrite!(&mut self.serial_buffer, "None"),
+                    }
+                    .ok();
+                    match self.clocks.dcf77_power_window() {
+                        Some((start, end)) => {
+                            ufmt::uwriteln!(&mut self.serial_buffer, " window {}-{}", start, end)
+                        }
+                        None => ufmt::uwriteln!(&mut self.serial_buffer, " window None"),
+                    }
+                    .ok();
+                }
+                #[cfg(feature = "dcf77-pon")]
+                Ok(Some(Command::SetDcf77PowerWindow(window))) => {
+                    self.clocks.set_dcf77_power_window(window);
+                    ufmt::uwriteln!(&mut self.serial_buffer, "Ack").ok();
+                }
+                #[cfg(feature = "dcf77-pon")]
+                Ok(Some(Command::SetDcf77PowerTimeout(timeout_minutes))) => {
+                    self.clocks.set_dcf77_power_timeout(timeout_minutes);
+                    ufmt::uwriteln!(&mut self.serial_buffer, "Ack").ok();
+                }
+                Ok(Some(Command::QueryTemperature)) => match self.clocks.temperature {
+                    Some(temperature) => {
+                        ufmt::uwriteln!(&mut self.serial_buffer, "{}", temperature).ok();
+                    }
+                    None => {
+                        ufmt::uwriteln!(&mut self.serial_buffer, "None").ok();
+                    }
+                },
                 Ok(Some(Command::QueryPhase)) => match self.clocks.phase_of_day {
                     PhaseOfDay::Default { day_last_set } => {
                         ufmt::uwrite!(&mut self.serial_buffer, "Default day last set ",).ok();
@@ -337,6 +579,10 @@ impl<const WRITE_BUFFER_SIZE: usize, const READ_BUFFER_SIZE: usize>
                     self.clocks.phase_of_day = PhaseOfDay::Default { day_last_set: None };
                     ufmt::uwriteln!(&mut self.serial_buffer, "Ack").ok();
                 }
+                Ok(Some(Command::SetDatetime(datetime))) => {
+                    self.clocks.set_datetime(datetime);
+                    ufmt::uwriteln!(&mut self.serial_buffer, "Ack").ok();
+                }
                 Ok(Some(Command::SetLedColor(color))) => {
                     self.forced_led_color = Some(color);
                     ufmt::uwriteln!(&mut self.serial_buffer, "Ack").ok();
@@ -350,7 +596,27 @@ impl<const WRITE_BUFFER_SIZE: usize, const READ_BUFFER_SIZE: usize>
                     ufmt::uwriteln!(&mut self.serial_buffer, "Ack").ok();
                 }
                 Ok(Some(Command::DebugDcf77)) => {
-                    self.debug_dcf77 = !self.debug_dcf77;
+                    self.debug_dcf77 = match self.debug_dcf77 {
+                        Dcf77DebugMode::Off => Dcf77DebugMode::Bits,
+                        _ => Dcf77DebugMode::Off,
+                    };
+                    ufmt::uwriteln!(&mut self.serial_buffer, "Debug dcf77: {}", self.debug_dcf77)
+                        .ok();
+                }
+                Ok(Some(Command::CaptureDcf77)) => match self.capture_dcf77.take() {
+                    Some(mut capture) => {
+                        if let Some(line) = capture.finish() {
+                            ufmt::uwriteln!(&mut self.serial_buffer, "{}", line).ok();
+                        }
+                        ufmt::uwriteln!(&mut self.serial_buffer, "Capture dcf77: false").ok();
+                    }
+                    None => {
+                        self.capture_dcf77 = Some(CaptureEncoder::default());
+                        ufmt::uwriteln!(&mut self.serial_buffer, "Capture dcf77: true").ok();
+                    }
+                },
+                Ok(Some(Command::SetDebugDcf77(mode))) => {
+                    self.debug_dcf77 = mode;
                     ufmt::uwriteln!(&mut self.serial_buffer, "Debug dcf77: {}", self.debug_dcf77)
                         .ok();
                 }
@@ -380,7 +646,16 @@ fn main() -> ! {
         { serial_commands::SERIAL_WRITE_BUFFER_SIZE },
         { serial_commands::SERIAL_READ_BUFFER_SIZE },
     > {
-        clocks: clocks::Clock::init(dp.TC0, pins.d2, i2c),
+        clocks: clocks::Clock::init(
+            dp.TC0,
+            #[cfg(feature = "dcf77-int0")]
+            dp.EXINT,
+            pins.d2,
+            #[cfg(feature = "dcf77-pon")]
+            pins.d5,
+            SelectedRtc::init(i2c),
+            arduino_hal::Eeprom::new(dp.EEPROM),
+        ),
         inputs: inputs::Inputs::init(
             pins.d4,
             BUTTON_LOGICAL_LEVEL_HIGH,
@@ -388,11 +663,22 @@ fn main() -> ! {
             LUMINOSITY_LOGICAL_LEVEL_HIGH,
             pins.d6,
             PROXIMITY_LOGICAL_LEVEL_HIGH,
+            pins.d12,
+            RTC_ALARM_LOGICAL_LEVEL_HIGH,
+        ),
+        outputs: outputs::Outputs::init(
+            pins.d11,
+            pins.d10,
+            pins.d13,
+            pins.d8,
+            pins.d7,
+            #[cfg(feature = "dcf77-tx")]
+            pins.d9,
         ),
-        outputs: outputs::Outputs::init(pins.d11, pins.d10, pins.d13, pins.d8, pins.d7),
         serial_buffer: Default::default(),
         forced_led_color: None,
-        debug_dcf77: false,
+        debug_dcf77: Dcf77DebugMode::Off,
+        capture_dcf77: None,
     };
 
     {
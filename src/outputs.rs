@@ -1,4 +1,6 @@
 //! Outputs of the clock setup, for humans
+#[cfg(feature = "dcf77-tx")]
+use crate::Dcf77TransmitterOutput;
 use crate::{
     BuzzerOutput, DisplaySpiClkOutput, DisplaySpiCsOutput, DisplaySpiMosiOutput,
     LedStripDataOutput, LED_STRIP_COUNT,
@@ -8,6 +10,8 @@ pub use display::DisplayIntensity;
 pub use led_strip::Color;
 
 mod buzzer;
+#[cfg(feature = "dcf77-tx")]
+mod dcf77_transmitter;
 mod display;
 mod led_strip;
 
@@ -18,6 +22,9 @@ pub struct Outputs {
     pub led_strip: led_strip::LedStrip<LED_STRIP_COUNT>,
     /// Buzzer alarm
     pub buzzer: buzzer::Buzzer,
+    /// Re-broadcast of the DCF77 signal
+    #[cfg(feature = "dcf77-tx")]
+    pub dcf77_transmitter: dcf77_transmitter::Dcf77Transmitter,
 }
 
 impl Outputs {
@@ -30,11 +37,14 @@ impl Outputs {
         clk: Pin<impl Io, DisplaySpiClkOutput>,
         led_strip_data: Pin<impl Io, LedStripDataOutput>,
         buzzer: Pin<impl Io, BuzzerOutput>,
+        #[cfg(feature = "dcf77-tx")] dcf77_transmitter: Pin<impl Io, Dcf77TransmitterOutput>,
     ) -> Self {
         Self {
             display: display::Display::init(data, cs, clk),
             led_strip: led_strip::LedStrip::init(led_strip_data),
             buzzer: buzzer::Buzzer::init(buzzer),
+            #[cfg(feature = "dcf77-tx")]
+            dcf77_transmitter: dcf77_transmitter::Dcf77Transmitter::init(dcf77_transmitter),
         }
     }
 
@@ -0,0 +1,73 @@
+//! Re-broadcast the DCF77 signal, encoding the current datetime:
+//! the pin drives a small local transmitter, whose carrier is reduced
+//! during the pulses. The seconds follow the ones of the RTC, up to
+//! the duration of a loop of the main structure.
+use crate::{
+    clocks::{
+        dcf77::Dcf77Encoder,
+        timer::{get_timer, Timer},
+        Datetime, SummerTimeRule, UtcOffset,
+    },
+    Dcf77TransmitterOutput, DCF77_TRANSMITTER_LOGICAL_LEVEL_HIGH,
+};
+use arduino_hal::port::{
+    mode::{Io, Output},
+    Pin, PinMode,
+};
+
+/// DCF77 transmitter structure
+pub struct Dcf77Transmitter {
+    /// Pin connected to the input of the transmitter
+    data_out: Pin<Output, Dcf77TransmitterOutput>,
+    encoder: Dcf77Encoder,
+    /// Second of the RTC during the last update, if any
+    second: Option<u8>,
+    /// Beginning and width, in ms, of the current pulse, if any
+    pulse: Option<(Option<Timer>, u16)>,
+}
+
+impl Dcf77Transmitter {
+    /// Initialize the object, the carrier being on.
+    pub fn init<MODE: PinMode + Io>(data_out: Pin<MODE, Dcf77TransmitterOutput>) -> Self {
+        let mut out = Self {
+            data_out: data_out.into_output(),
+            encoder: Dcf77Encoder::default(),
+            second: None,
+            pulse: None,
+        };
+        out.set_carrier_reduced(false);
+        out
+    }
+
+    /// Update the output, given the current local datetime, if any, its offset
+    /// to UTC and the rule of summer time: a pulse begins when the second changes.
+    pub fn update(
+        &mut self,
+        datetime: Option<Datetime>,
+        utc_offset: UtcOffset,
+        rule: &SummerTimeRule,
+    ) {
+        let second = datetime.and_then(|datetime| datetime.time.second);
+        if second != self.second {
+            self.second = second;
+            self.pulse = datetime
+                .and_then(|datetime| self.encoder.pulse_ms(&datetime, utc_offset, rule))
+                .map(|width| (get_timer(), width));
+        }
+        let reduced = match (self.pulse, get_timer()) {
+            (Some((Some(start), width)), Some(now)) => (now - start).0 < width,
+            _ => false,
+        };
+        self.set_carrier_reduced(reduced);
+    }
+
+    /// Set the pin to the appropriate electrical level
+    /// to reduce the carrier, or not.
+    fn set_carrier_reduced(&mut self, reduced: bool) {
+        if reduced == DCF77_TRANSMITTER_LOGICAL_LEVEL_HIGH {
+            self.data_out.set_high();
+        } else {
+            self.data_out.set_low();
+        }
+    }
+}
@@ -1,6 +1,9 @@
 //! Control the sequential four 8x8 matrix LED display panel.
 // https://www.analog.com/media/en/technical-documentation/data-sheets/max7219-max7221.pdf
-use crate::{clocks::Datetime, DisplaySpiClkOutput, DisplaySpiCsOutput, DisplaySpiMosiOutput};
+use crate::{
+    clocks::{rtc::Temperature, ClockDatetime},
+    DisplaySpiClkOutput, DisplaySpiCsOutput, DisplaySpiMosiOutput,
+};
 use arduino_hal::port::{
     mode::{self, Io},
     Pin,
@@ -86,40 +89,93 @@ impl Display {
         self.frame_buffer.set_at(column_index, value);
     }
 
-    /// Write the time HH:MM at the beginning of the display.
-    pub fn write_time(&mut self, datetime: Option<Datetime>) {
+    /// Write the time HH:MM at the beginning of the display,
+    /// preceded by ! if not trustworthy.
+    pub fn write_time(&mut self, datetime: ClockDatetime) {
         self.frame_buffer.clear();
-        match datetime {
-            Some(datetime) => {
+        self.push_time(datetime, false);
+    }
+
+    /// Write the time HH:MM, preceded by ! if not trustworthy, followed by
+    /// the temperature rounded to the degree (from -9 to 99), with narrow digits
+    /// so that both fit before the bars at the end of the display.
+    pub fn write_time_and_temperature(
+        &mut self,
+        datetime: ClockDatetime,
+        temperature: Option<Temperature>,
+    ) {
+        self.frame_buffer.clear();
+        self.push_time(datetime, true);
+        self.frame_buffer.push(&[0]);
+        match temperature.map(|temperature| temperature.degrees().clamp(-9, 99)) {
+            Some(degrees) => {
+                if degrees < 0 {
+                    self.frame_buffer.push(Symbols::Dash.narrow());
+                } else if degrees >= 10 {
+                    self.frame_buffer
+                        .push(Symbols::from(degrees as u8 / 10).narrow());
+                } else {
+                    self.frame_buffer.push(&[0, 0, 0]);
+                }
                 self.frame_buffer.push(&[0]);
+                self.frame_buffer
+                    .push(Symbols::from(degrees.unsigned_abs() as u8 % 10).narrow());
+            }
+            None => {
+                self.frame_buffer.push(Symbols::Dash.narrow());
+                self.frame_buffer.push(&[0]);
+                self.frame_buffer.push(Symbols::Dash.narrow());
+            }
+        }
+    }
+
+    /// Push the time HH:MM, preceded by ! if not trustworthy,
+    /// with digits 5 columns wide, or 3 if narrow.
+    fn push_time(&mut self, datetime: ClockDatetime, narrow: bool) {
+        let symbol = |symbol: Symbols| {
+            if narrow {
+                symbol.narrow()
+            } else {
+                symbol.into()
+            }
+        };
+        let untrusted = matches!(datetime, ClockDatetime::Untrusted(_));
+        match datetime.unchecked() {
+            Some(datetime) => {
+                // Mark a time which is not trustworthy
+                if untrusted {
+                    self.frame_buffer.push(Symbols::Exclamation.into());
+                } else {
+                    self.frame_buffer.push(&[0]);
+                }
                 self.frame_buffer.push(&[0]);
 
                 self.frame_buffer
-                    .push(Symbols::from(datetime.time.hour / 10).into());
+                    .push(symbol(Symbols::from(datetime.time.hour / 10)));
                 self.frame_buffer.push(&[0]);
                 self.frame_buffer
-                    .push(Symbols::from(datetime.time.hour % 10).into());
+                    .push(symbol(Symbols::from(datetime.time.hour % 10)));
 
                 self.frame_buffer.push(&[0]);
                 self.frame_buffer.push(Symbols::Colon.into());
                 self.frame_buffer.push(&[0]);
 
                 self.frame_buffer
-                    .push(Symbols::from(datetime.time.minute / 10).into());
+                    .push(symbol(Symbols::from(datetime.time.minute / 10)));
                 self.frame_buffer.push(&[0]);
                 self.frame_buffer
-                    .push(Symbols::from(datetime.time.minute % 10).into());
+                    .push(symbol(Symbols::from(datetime.time.minute % 10)));
 
                 self.frame_buffer.push(&[0]);
             }
             None => {
-                self.frame_buffer.push(Symbols::Dash.into());
-                self.frame_buffer.push(Symbols::Dash.into());
+                self.frame_buffer.push(symbol(Symbols::Dash));
+                self.frame_buffer.push(symbol(Symbols::Dash));
                 self.frame_buffer.push(&[0]);
                 self.frame_buffer.push(Symbols::Colon.into());
                 self.frame_buffer.push(&[0]);
-                self.frame_buffer.push(Symbols::Dash.into());
-                self.frame_buffer.push(Symbols::Dash.into());
+                self.frame_buffer.push(symbol(Symbols::Dash));
+                self.frame_buffer.push(symbol(Symbols::Dash));
             }
         };
     }
@@ -297,6 +353,7 @@ enum Symbols {
     _8,
     _9,
     Colon,
+    Exclamation,
     Dash,
 }
 
@@ -336,7 +393,29 @@ impl From<Symbols> for &[u8] {
             Symbols::_8 => &[0x36, 0x49, 0x49, 0x49, 0x36],
             Symbols::_9 => &[0x26, 0x49, 0x49, 0x49, 0x3e],
             Symbols::Colon => &[0x14],
+            Symbols::Exclamation => &[0x5f],
             Symbols::Dash => &[0x00, 0x08, 0x08, 0x08, 0x00],
         }
     }
 }
+
+impl Symbols {
+    /// Narrow version of a symbol: digits are 3 columns wide,
+    /// over the 5 lower rows.
+    fn narrow(self) -> &'static [u8] {
+        match self {
+            Symbols::_0 => &[0x7c, 0x44, 0x7c],
+            Symbols::_1 => &[0x48, 0x7c, 0x40],
+            Symbols::_2 => &[0x74, 0x54, 0x5c],
+            Symbols::_3 => &[0x54, 0x54, 0x7c],
+            Symbols::_4 => &[0x1c, 0x10, 0x7c],
+            Symbols::_5 => &[0x5c, 0x54, 0x74],
+            Symbols::_6 => &[0x7c, 0x54, 0x74],
+            Symbols::_7 => &[0x04, 0x04, 0x7c],
+            Symbols::_8 => &[0x7c, 0x54, 0x7c],
+            Symbols::_9 => &[0x5c, 0x54, 0x7c],
+            Symbols::Dash => &[0x10, 0x10, 0x10],
+            symbol => symbol.into(),
+        }
+    }
+}
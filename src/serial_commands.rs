@@ -1,12 +1,15 @@
 //! Serial communication, with commands from the user
-use crate::{clocks::Time, outputs::Color};
+use crate::{
+    clocks::{Date, Datetime, DayOfWeek, Time},
+    outputs::Color,
+};
 use arduino_hal::hal::usart::Usart0;
 use core::cell::RefCell;
 use embedded_hal::serial::Read;
 use ringbuffer::{ConstGenericRingBuffer, RingBuffer};
 
 /// Size, in bytes, of the buffer for serial input
-pub const SERIAL_READ_BUFFER_SIZE: usize = 14;
+pub const SERIAL_READ_BUFFER_SIZE: usize = 18;
 /// Size, in bytes, of the buffer for serial output
 pub const SERIAL_WRITE_BUFFER_SIZE: usize = 64;
 
@@ -63,6 +66,34 @@ pub enum SunriseSelection {
     WeekEnd,
 }
 
+/// Traces of the DCF77 decoder printed on the serial port
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Dcf77DebugMode {
+    /// No trace
+    Off,
+    /// Decoded bits only
+    Bits,
+    /// Errors of the decoder only
+    Errors,
+    /// Decoded bits interleaved with the errors
+    Full,
+}
+
+impl ufmt::uDisplay for Dcf77DebugMode {
+    /// Format the debug mode to display on the serial port.
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        match self {
+            Dcf77DebugMode::Off => f.write_str("off"),
+            Dcf77DebugMode::Bits => f.write_str("bits"),
+            Dcf77DebugMode::Errors => f.write_str("errors"),
+            Dcf77DebugMode::Full => f.write_str("full"),
+        }
+    }
+}
+
 /// Commands for the serial interfaces
 #[derive(PartialEq, Eq)]
 pub enum Command {
@@ -72,8 +103,35 @@ pub enum Command {
     QueryDawnDuration,
     /// Query last DCF77 update: ?77
     QueryLastDcf77Update,
-    /// Debug dcf77: !dbg77
+    /// Query the number of rejected DCF77 frames: ?r77
+    QueryRejectedDcf77Frames,
+    /// Capture raw dcf77 polled values, toggle: !cap77
+    CaptureDcf77,
+    /// Query the last DCF77 updates of the RTC: ?h77
+    QueryDcf77History,
+    /// Query the last measures of the drift of the RTC: ?drift
+    QueryRtcDrift,
+    /// Query the histogram of the DCF77 reception by hour of the day: ?hist
+    QueryReceptionHistogram,
+    /// Query the metrics of the DCF77 reception quality: ?q77
+    QueryDcf77Quality,
+    /// Debug dcf77, toggle the bits trace: !dbg77
     DebugDcf77,
+    /// Debug dcf77, select the trace (off, bits, errors, full): !dbg77 \[obef\]
+    SetDebugDcf77(Dcf77DebugMode),
+    /// Query the power of the DCF77 receiver, its timeout and its window: ?pon
+    #[cfg(feature = "dcf77-pon")]
+    QueryDcf77Power,
+    /// Set the window of the day when the DCF77 receiver is powered,
+    /// or power it all day: !pon \[HH:MM HH:MM\]
+    #[cfg(feature = "dcf77-pon")]
+    SetDcf77PowerWindow(Option<(Time, Time)>),
+    /// Set the minutes without any DCF77 frame before power-cycling the receiver,
+    /// 00 to disable: !pto MM
+    #[cfg(feature = "dcf77-pon")]
+    SetDcf77PowerTimeout(Option<u16>),
+    /// Query the temperature of the RTC: ?tmp
+    QueryTemperature,
     /// Query the current phase of the day: ?phase
     QueryPhase,
     /// Query the time of sunrise (alarm), week or week-end: ?w\[ke\]
@@ -84,6 +142,8 @@ pub enum Command {
     Set(SunriseSelection, Time),
     /// Set the color of the led stripe: !led rr,gg,bb
     SetLedColor(Color),
+    /// Set the datetime by hand, local time: !dt YYMMDD HHMMSS
+    SetDatetime(Datetime),
     /// Reset led color: !led
     ResetLedColor,
     /// Ack alarm: !ack
@@ -142,11 +202,35 @@ impl<const WRITE_BUFFER_SIZE: usize, const READ_BUFFER_SIZE: usize>
             Some(4) => match self.dequeue_to_array() {
                 [b'!', b'l', b'e', b'd'] => Ok(Some(Command::ResetLedColor)),
                 [b'!', b'a', b'c', b'k'] => Ok(Some(Command::AckAlarm)),
+                [b'?', b'r', b'7', b'7'] => Ok(Some(Command::QueryRejectedDcf77Frames)),
+                [b'?', b'q', b'7', b'7'] => Ok(Some(Command::QueryDcf77Quality)),
+                [b'?', b'h', b'7', b'7'] => Ok(Some(Command::QueryDcf77History)),
+                [b'?', b't', b'm', b'p'] => Ok(Some(Command::QueryTemperature)),
+                #[cfg(feature = "dcf77-pon")]
+                [b'?', b'p', b'o', b'n'] => Ok(Some(Command::QueryDcf77Power)),
+                #[cfg(feature = "dcf77-pon")]
+                [b'!', b'p', b'o', b'n'] => Ok(Some(Command::SetDcf77PowerWindow(None))),
+                _ => Err(()),
+            },
+            Some(5) => match self.dequeue_to_array() {
+                [b'?', b'h', b'i', b's', b't'] => Ok(Some(Command::QueryReceptionHistogram)),
                 _ => Err(()),
             },
             Some(6) => match self.dequeue_to_array() {
                 [b'?', b'p', b'h', b'a', b's', b'e'] => Ok(Some(Command::QueryPhase)),
                 [b'!', b'd', b'b', b'g', b'7', b'7'] => Ok(Some(Command::DebugDcf77)),
+                [b'!', b'c', b'a', b'p', b'7', b'7'] => Ok(Some(Command::CaptureDcf77)),
+                [b'?', b'd', b'r', b'i', b'f', b't'] => Ok(Some(Command::QueryRtcDrift)),
+                _ => Err(()),
+            },
+            #[cfg(feature = "dcf77-pon")]
+            Some(7) => match self.dequeue_to_array() {
+                [b'!', b'p', b't', b'o', b' ', m1, m2] => {
+                    let minutes = Self::decode_two_ascii_digits(m1, m2, 10)?;
+                    Ok(Some(Command::SetDcf77PowerTimeout(
+                        (minutes > 0).then_some(minutes as u16),
+                    )))
+                }
                 _ => Err(()),
             },
             Some(8) => match self.dequeue_to_array() {
@@ -154,6 +238,13 @@ impl<const WRITE_BUFFER_SIZE: usize, const READ_BUFFER_SIZE: usize>
                     let minute = Self::decode_two_ascii_digits(m1, m2, 10)?;
                     Ok(Some(Command::SetDawn(minute)))
                 }
+                [b'!', b'd', b'b', b'g', b'7', b'7', b' ', mode] => match mode {
+                    b'o' => Ok(Some(Command::SetDebugDcf77(Dcf77DebugMode::Off))),
+                    b'b' => Ok(Some(Command::SetDebugDcf77(Dcf77DebugMode::Bits))),
+                    b'e' => Ok(Some(Command::SetDebugDcf77(Dcf77DebugMode::Errors))),
+                    b'f' => Ok(Some(Command::SetDebugDcf77(Dcf77DebugMode::Full))),
+                    _ => Err(()),
+                },
                 _ => Err(()),
             },
             Some(9) => match self.dequeue_to_array() {
@@ -184,6 +275,48 @@ impl<const WRITE_BUFFER_SIZE: usize, const READ_BUFFER_SIZE: usize>
                 }
                 _ => Err(()),
             },
+            #[cfg(feature = "dcf77-pon")]
+            Some(16) => match self.dequeue_to_array() {
+                [b'!', b'p', b'o', b'n', b' ', h1, h2, b':', m1, m2, b' ', h3, h4, b':', m3, m4] => {
+                    let start = Self::decode_time(h1, h2, m1, m2)?;
+                    let end = Self::decode_time(h3, h4, m3, m4)?;
+                    Ok(Some(Command::SetDcf77PowerWindow(Some((start, end)))))
+                }
+                _ => Err(()),
+            },
+            Some(17) => match self.dequeue_to_array() {
+                [b'!', b'd', b't', b' ', y1, y2, mo1, mo2, d1, d2, b' ', h1, h2, mi1, mi2, s1, s2] =>
+                {
+                    let date = Date {
+                        day: Self::decode_two_ascii_digits(d1, d2, 10)?,
+                        month: Self::decode_two_ascii_digits(mo1, mo2, 10)?,
+                        year: Self::decode_two_ascii_digits(y1, y2, 10)?,
+                        weekday: DayOfWeek::Monday,
+                    };
+                    let time = Time {
+                        hour: Self::decode_two_ascii_digits(h1, h2, 10)?,
+                        minute: Self::decode_two_ascii_digits(mi1, mi2, 10)?,
+                        second: Some(Self::decode_two_ascii_digits(s1, s2, 10)?),
+                    };
+                    if date.year > 99
+                        || !(1..=12).contains(&date.month)
+                        || !(1..=date.days_in_month()).contains(&date.day)
+                        || time.hour > 23
+                        || time.minute > 59
+                        || time.second > Some(59)
+                    {
+                        return Err(());
+                    }
+                    Ok(Some(Command::SetDatetime(Datetime {
+                        date: Date {
+                            weekday: date.computed_weekday(),
+                            ..date
+                        },
+                        time,
+                    })))
+                }
+                _ => Err(()),
+            },
             Some(_) => {
                 while Some(b'\n') != self.input.dequeue() {}
                 Err(())
@@ -204,6 +337,20 @@ impl<const WRITE_BUFFER_SIZE: usize, const READ_BUFFER_SIZE: usize>
         array
     }
 
+    /// Decode a time h1h2:m1m2, where the digits are in ascii
+    #[cfg(feature = "dcf77-pon")]
+    fn decode_time(h1: u8, h2: u8, m1: u8, m2: u8) -> Result<Time, ()> {
+        let time = Time {
+            hour: Self::decode_two_ascii_digits(h1, h2, 10)?,
+            minute: Self::decode_two_ascii_digits(m1, m2, 10)?,
+            second: None,
+        };
+        if time.hour > 23 || time.minute > 59 {
+            return Err(());
+        }
+        Ok(time)
+    }
+
     /// Decode d1d2 where d1 and d2 are digits in ascii, in a given base
     fn decode_two_ascii_digits(d1: u8, d2: u8, base: u8) -> Result<u8, ()> {
         Ok(base * Self::decode_ascii_digit(d1)? + Self::decode_ascii_digit(d2)?)
@@ -0,0 +1,60 @@
+#!/usr/bin/env python3
+"""Record the raw DCF77 polled values streamed by the alarm clock.
+
+The capture mode is toggled on the board with the `!cap77` command, and the
+board streams lines `$<timer> <count> <hex samples>` (see
+`decoder/src/dcf77/capture.rs`). This tool keeps these lines, without the
+leading `$`, in a capture file:
+
+    # dcf77 capture <ISO date of the recording>
+    <timer of the first sample> <number of samples> <samples in hex>
+    ...
+
+Lines beginning with `#` are comments. Timers are in milliseconds and wrap
+at 65525; samples are 25 ms apart, packed by four per hexadecimal digit,
+the oldest one in the most significant bit.
+
+Usage: dcf77_capture.py /dev/ttyACM0 capture.txt [--duration SECONDS]
+"""
+import argparse
+import datetime
+import sys
+import time
+
+import serial
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("port", help="serial port of the board")
+    parser.add_argument("output", help="capture file to write")
+    parser.add_argument("--baudrate", type=int, default=115200)
+    parser.add_argument(
+        "--duration", type=float, default=None, help="stop after this many seconds"
+    )
+    args = parser.parse_args()
+
+    with serial.Serial(args.port, args.baudrate, timeout=1) as port, open(
+        args.output, "w"
+    ) as output:
+        output.write(f"# dcf77 capture {datetime.datetime.now().isoformat()}\n")
+        port.write(b"!cap77\n")
+        start = time.monotonic()
+        lines = 0
+        try:
+            while args.duration is None or time.monotonic() - start < args.duration:
+                line = port.readline().decode("ascii", errors="replace").strip()
+                if line.startswith("$"):
+                    output.write(line[1:] + "\n")
+                    lines += 1
+                elif line:
+                    print(line, file=sys.stderr)
+        except KeyboardInterrupt:
+            pass
+        finally:
+            port.write(b"!cap77\n")
+        print(f"{lines} lines recorded in {args.output}", file=sys.stderr)
+
+
+if __name__ == "__main__":
+    main()